@@ -0,0 +1,601 @@
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_4};
+use std::rc::Rc;
+
+use raylib::prelude::*;
+
+use crate::animation_clock::AnimationClock;
+use crate::json;
+use crate::textures::TextureManager;
+
+pub struct SpriteAnimation {
+    texture: Rc<Texture2D>, // shared with other animations cut from the same sheet
+    frame_width: f32,
+    frame_height: f32,
+    row: u32, // which row of the sheet this animation's frames are on
+    clock: AnimationClock,
+}
+
+impl SpriteAnimation {
+    // Takes a shared sheet or a Texture2D of its own
+    pub fn new(sprite: impl Into<Rc<Texture2D>>, num_frames: u32, speed: u32, looping: bool) -> SpriteAnimation {
+        let sprite = sprite.into();
+        let num_frames = num_frames.max(1);
+        // A frame width with a fraction would sample across pixel boundaries
+        // and bleed the neighbouring frame in, so drop the leftover columns
+        if !frames_divide_evenly(sprite.width, num_frames) {
+            eprintln!(
+                "WARNING: sheet width {} doesn't split into {} frames, ignoring the last {} pixel columns",
+                sprite.width, num_frames, sprite.width as u32 % num_frames
+            );
+        }
+        let frame_width = (sprite.width as u32 / num_frames) as f32;
+        let frame_height = sprite.height as f32;
+        SpriteAnimation { 
+            texture: sprite, 
+            frame_width,
+            frame_height,
+            row: 0,
+            clock: AnimationClock::new(num_frames, speed, looping),
+        }
+    }
+
+    // Swaps in a reloaded copy of the sheet, which may have grown or shrunk,
+    // if it's drawing from old. Stays on the frame it was showing.
+    pub fn replace_texture(&mut self, old: &Rc<Texture2D>, new: &Rc<Texture2D>) {
+        if !Rc::ptr_eq(&self.texture, old) {
+            return;
+        }
+        self.frame_width = (new.width as u32 / self.clock.num_frames()) as f32;
+        if old.height > 0 {
+            self.frame_height = (self.frame_height * new.height as f32 / old.height as f32).floor();
+        }
+        self.texture = Rc::clone(new);
+    }
+
+    // For sheets that stack several animations in rows of frame_height pixels
+    pub fn set_row(&mut self, row: u32, frame_height: f32) {
+        self.row = row;
+        self.frame_height = frame_height;
+    }
+
+    pub fn set_event_frames(&mut self, frames: Vec<u32>) {
+        self.clock.set_event_frames(frames);
+    }
+
+    // See AnimationClock::set_frame_durations
+    pub fn with_frame_durations(mut self, durations: Vec<f32>) -> SpriteAnimation {
+        self.clock.set_frame_durations(durations);
+        self
+    }
+
+    pub fn reset(&mut self) {
+        self.clock.reset();
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.clock.is_finished()
+    }
+
+    // See AnimationClock::progress
+    pub fn progress(&self) -> f32 {
+        self.clock.progress()
+    }
+
+    // Returns the event frames entered this tick, in the order they were hit
+    pub fn animate(&mut self, dt: f32) -> Vec<u32> {
+        self.clock.advance(dt)
+    }
+
+    pub fn draw(&self, pos: Vector2, scale: f32, flip_h: bool, tint: Color, d: &mut RaylibDrawHandle) {
+        // A negative source width makes raylib mirror the frame in place
+        let source_width = if flip_h { -self.frame_width } else { self.frame_width };
+        let source_rec = Rectangle::new(
+            (self.clock.current_frame() as f32 * self.frame_width).floor(), 
+            (self.row as f32 * self.frame_height).floor(), 
+            source_width, 
+            self.frame_height
+        );
+
+        let dest_rec = Rectangle::new(pos.x, pos.y, self.frame_width * scale, self.frame_height * scale);
+        d.draw_texture_pro(
+            &*self.texture,
+            source_rec,
+            dest_rec,
+            Vector2::new(0.0, 0.0), // Origin (for rotation/scaling)
+            0.0,                    // Rotation
+            tint,
+        );
+    }
+}
+
+// Sets up a SpriteAnimation by name rather than by argument order. By
+// default it's one frame at 10 frames per second, looping, on the top row.
+pub struct SpriteAnimationBuilder {
+    texture: Rc<Texture2D>,
+    num_frames: u32,
+    speed: u32, // frames per second
+    looping: bool,
+    row: Option<(u32, f32)>, // and its frame height
+    event_frames: Vec<u32>,
+    frame_durations: Vec<f32>,
+}
+
+impl SpriteAnimationBuilder {
+    pub fn new(texture: impl Into<Rc<Texture2D>>) -> SpriteAnimationBuilder {
+        SpriteAnimationBuilder {
+            texture: texture.into(),
+            num_frames: 1,
+            speed: 10,
+            looping: true,
+            row: None,
+            event_frames: Vec::new(),
+            frame_durations: Vec::new(),
+        }
+    }
+
+    pub fn frames(mut self, num_frames: u32) -> SpriteAnimationBuilder {
+        self.num_frames = num_frames;
+        self
+    }
+
+    pub fn speed(mut self, frames_per_second: u32) -> SpriteAnimationBuilder {
+        self.speed = frames_per_second;
+        self
+    }
+
+    pub fn looping(mut self, looping: bool) -> SpriteAnimationBuilder {
+        self.looping = looping;
+        self
+    }
+
+    // See SpriteAnimation::set_row
+    pub fn row(mut self, row: u32, frame_height: f32) -> SpriteAnimationBuilder {
+        self.row = Some((row, frame_height));
+        self
+    }
+
+    pub fn event_frames(mut self, frames: Vec<u32>) -> SpriteAnimationBuilder {
+        self.event_frames = frames;
+        self
+    }
+
+    // See SpriteAnimation::with_frame_durations
+    pub fn frame_durations(mut self, durations: Vec<f32>) -> SpriteAnimationBuilder {
+        self.frame_durations = durations;
+        self
+    }
+
+    pub fn build(self) -> SpriteAnimation {
+        let mut animation = SpriteAnimation::new(self.texture, self.num_frames, self.speed, self.looping)
+            .with_frame_durations(self.frame_durations);
+        if let Some((row, frame_height)) = self.row {
+            animation.set_row(row, frame_height);
+        }
+        animation.set_event_frames(self.event_frames);
+        animation
+    }
+}
+
+pub fn frames_divide_evenly(sheet_width: i32, num_frames: u32) -> bool {
+    num_frames > 0 && (sheet_width as u32).is_multiple_of(num_frames)
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum AnimationType {
+    Idle(Direction),
+    Run(Direction),
+    Attack1(Direction),
+    Attack2(Direction),
+    Death(Direction),
+}
+
+impl AnimationType {
+    pub fn with_direction(&self, direction: Direction) -> AnimationType {
+        match self {
+            Self::Idle(_) => Self::Idle(direction),
+            Self::Run(_) => Self::Run(direction),
+            Self::Attack1(_) => Self::Attack1(direction),
+            Self::Attack2(_) => Self::Attack2(direction),
+            Self::Death(_) => Self::Death(direction),
+        }
+    }
+
+    pub fn from_name(name: &str, direction: Direction) -> Option<AnimationType> {
+        match name {
+            "Idle" => Some(Self::Idle(direction)),
+            "Run" => Some(Self::Run(direction)),
+            "Attack1" => Some(Self::Attack1(direction)),
+            "Attack2" => Some(Self::Attack2(direction)),
+            "Death" => Some(Self::Death(direction)),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Idle(_) => "Idle",
+            Self::Run(_) => "Run",
+            Self::Attack1(_) => "Attack1",
+            Self::Attack2(_) => "Attack2",
+            Self::Death(_) => "Death",
+        }
+    }
+
+    pub fn direction(&self) -> Direction {
+        match self {
+            Self::Idle(d) | Self::Run(d) | Self::Attack1(d) | Self::Attack2(d) | Self::Death(d) => *d,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    pub fn from_name(name: &str) -> Option<Direction> {
+        match name {
+            "Up" => Some(Self::Up),
+            "Down" => Some(Self::Down),
+            "Left" => Some(Self::Left),
+            "Right" => Some(Self::Right),
+            "UpLeft" => Some(Self::UpLeft),
+            "UpRight" => Some(Self::UpRight),
+            "DownLeft" => Some(Self::DownLeft),
+            "DownRight" => Some(Self::DownRight),
+            _ => None,
+        }
+    }
+
+    // Angle in radians from +x, clockwise on screen since y points down.
+    // Each direction owns the 45 degree sector centered on it: Right is
+    // -22.5..22.5, DownRight 22.5..67.5, Down 67.5..112.5 and so on round to
+    // UpRight at -67.5..-22.5.
+    pub fn from_angle(angle: f32) -> Direction {
+        const CLOCKWISE: [Direction; 8] = [
+            Direction::Right, Direction::DownRight, Direction::Down, Direction::DownLeft,
+            Direction::Left, Direction::UpLeft, Direction::Up, Direction::UpRight,
+        ];
+        CLOCKWISE[(angle / FRAC_PI_4).round().rem_euclid(8.0) as usize]
+    }
+
+    // Unit length, diagonals included
+    pub fn to_vector(self) -> Vector2 {
+        let d = FRAC_1_SQRT_2;
+        match self {
+            Self::Up => Vector2::new(0.0, -1.0),
+            Self::Down => Vector2::new(0.0, 1.0),
+            Self::Left => Vector2::new(-1.0, 0.0),
+            Self::Right => Vector2::new(1.0, 0.0),
+            Self::UpLeft => Vector2::new(-d, -d),
+            Self::UpRight => Vector2::new(d, -d),
+            Self::DownLeft => Vector2::new(-d, d),
+            Self::DownRight => Vector2::new(d, d),
+        }
+    }
+
+    pub fn is_diagonal(self) -> bool {
+        matches!(self, Self::UpLeft | Self::UpRight | Self::DownLeft | Self::DownRight)
+    }
+
+    // The cardinal a diagonal is drawn as when it has no animation of its
+    // own. Side views read better than front or back ones for diagonal
+    // movement, so it's always the horizontal half.
+    pub fn nearest_cardinal(self) -> Direction {
+        match self {
+            Self::UpLeft | Self::DownLeft => Self::Left,
+            Self::UpRight | Self::DownRight => Self::Right,
+            cardinal => cardinal,
+        }
+    }
+
+    // The right-facing direction a left-facing one is the mirror image of
+    fn mirror_source(self) -> Option<Direction> {
+        match self {
+            Self::Left => Some(Self::Right),
+            Self::UpLeft => Some(Self::UpRight),
+            Self::DownLeft => Some(Self::DownRight),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Up => "Up",
+            Self::Down => "Down",
+            Self::Left => "Left",
+            Self::Right => "Right",
+            Self::UpLeft => "UpLeft",
+            Self::UpRight => "UpRight",
+            Self::DownLeft => "DownLeft",
+            Self::DownRight => "DownRight",
+        }
+    }
+}
+
+// The cardinal a velocity mostly points along, horizontal on an exact
+// diagonal. A zero vector has no direction, so it keeps the last one.
+pub fn direction_from_vector(v: Vector2, last: Direction) -> Direction {
+    if v == Vector2::zero() {
+        last
+    } else if v.x.abs() >= v.y.abs() {
+        if v.x > 0.0 { Direction::Right } else { Direction::Left }
+    } else if v.y > 0.0 {
+        Direction::Down
+    } else {
+        Direction::Up
+    }
+}
+
+pub const FALLBACK_ANIMATION: AnimationType = AnimationType::Idle(Direction::Down);
+
+// A set of animations keyed by type with one of them playing, shared by
+// anything drawn from a sprite sheet
+pub struct AnimatedSprite {
+    animations: HashMap<AnimationType, SpriteAnimation>,
+    current: AnimationType,
+    missing_animations: HashSet<AnimationType>, // already warned about
+    pub scale: f32,
+    flash_color: Color,
+    flash_timer: f32,
+}
+
+impl AnimatedSprite {
+    pub fn new(scale: f32) -> AnimatedSprite {
+        AnimatedSprite {
+            animations: HashMap::new(),
+            current: AnimationType::Idle(Direction::Down),
+            missing_animations: HashSet::new(),
+            scale,
+            flash_color: Color::WHITE,
+            flash_timer: 0.0,
+        }
+    }
+
+    pub fn add_animation(&mut self, animation_type: AnimationType, animation: SpriteAnimation) -> &mut SpriteAnimation {
+        self.animations.entry(animation_type).insert_entry(animation).into_mut()
+    }
+
+    pub fn current(&self) -> AnimationType {
+        self.current
+    }
+
+    pub fn change(&mut self, animation_type: AnimationType) {
+        self.current = animation_type;
+    }
+
+    // Left-facing animations that weren't loaded are drawn as the right-facing
+    // one mirrored, so only one of each pair has to be shipped. Diagonals
+    // that still aren't found fall back to their nearest cardinal, so sheets
+    // without any keep working.
+    fn resolve_animation(&self, animation_type: AnimationType) -> Option<(AnimationType, bool)> {
+        let direction = animation_type.direction();
+        if self.animations.contains_key(&animation_type) {
+            Some((animation_type, false))
+        } else if let Some(source) = direction.mirror_source()
+            && self.animations.contains_key(&animation_type.with_direction(source))
+        {
+            Some((animation_type.with_direction(source), true))
+        } else if direction.is_diagonal() {
+            self.resolve_animation(animation_type.with_direction(direction.nearest_cardinal()))
+        } else {
+            None
+        }
+    }
+
+    // See SpriteAnimation::replace_texture
+    pub fn replace_texture(&mut self, old: &Rc<Texture2D>, new: &Rc<Texture2D>) {
+        for animation in self.animations.values_mut() {
+            animation.replace_texture(old, new);
+        }
+    }
+
+    // Whether the animation, or something standing in for it, can be drawn
+    pub fn has_animation(&self, animation_type: AnimationType) -> bool {
+        self.resolve_animation(animation_type).is_some()
+    }
+
+    fn current_mut(&mut self) -> Option<&mut SpriteAnimation> {
+        let (key, _) = self.resolve_animation(self.current)?;
+        self.animations.get_mut(&key)
+    }
+
+    // Washes the sprite out towards color for duration seconds, counting
+    // down as it animates
+    pub fn flash(&mut self, color: Color, duration: f32) {
+        self.flash_color = color;
+        self.flash_timer = duration;
+    }
+
+    pub fn is_flashing(&self) -> bool {
+        self.flash_timer > 0.0
+    }
+
+    // Returns the event frames of the current animation entered this tick
+    pub fn animate(&mut self, dt: f32) -> Vec<u32> {
+        self.flash_timer = (self.flash_timer - dt).max(0.0);
+        if self.resolve_animation(self.current).is_none() {
+            if self.missing_animations.insert(self.current) {
+                eprintln!(
+                    "WARNING: no {:?} animation loaded, falling back to {:?}",
+                    self.current, FALLBACK_ANIMATION
+                );
+            }
+            self.current = FALLBACK_ANIMATION;
+        }
+
+        match self.current_mut() {
+            Some(animation) => animation.animate(dt),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn current_frame(&self) -> u32 {
+        self.resolve_animation(self.current)
+            .and_then(|(key, _)| self.animations.get(&key))
+            .map_or(0, |animation| animation.clock.current_frame())
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.resolve_animation(self.current)
+            .and_then(|(key, _)| self.animations.get(&key))
+            .map_or(0, |animation| animation.clock.num_frames())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.resolve_animation(self.current)
+            .and_then(|(key, _)| self.animations.get(&key))
+            .is_some_and(SpriteAnimation::is_finished)
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.resolve_animation(self.current)
+            .and_then(|(key, _)| self.animations.get(&key))
+            .map_or(0.0, SpriteAnimation::progress)
+    }
+
+    pub fn reset(&mut self) {
+        if let Some(animation) = self.current_mut() {
+            animation.reset();
+        }
+    }
+
+    // Area the current frame covers when drawn at pos
+    pub fn bounds(&self, pos: Vector2) -> Rectangle {
+        match self.resolve_animation(self.current).and_then(|(key, _)| self.animations.get(&key)) {
+            Some(animation) => Rectangle::new(
+                pos.x, pos.y,
+                animation.frame_width * self.scale,
+                animation.frame_height * self.scale,
+            ),
+            None => Rectangle::new(pos.x, pos.y, 0.0, 0.0),
+        }
+    }
+
+    pub fn draw(&self, pos: Vector2, view: Rectangle, d: &mut RaylibDrawHandle) {
+        self.draw_tinted(pos, view, Color::WHITE, d);
+    }
+
+    // Skips drawing when the frame is entirely outside view
+    pub fn draw_tinted(&self, pos: Vector2, view: Rectangle, tint: Color, d: &mut RaylibDrawHandle) {
+        if !self.bounds(pos).check_collision_recs(&view) {
+            return;
+        }
+        if let Some((key, flip_h)) = self.resolve_animation(self.current)
+            && let Some(animation) = self.animations.get(&key)
+        {
+            animation.draw(pos, self.scale, flip_h, tint, d);
+            if self.is_flashing() {
+                // Drawing the frame again additively washes it out towards the color
+                let mut d = d.begin_blend_mode(BlendMode::BLEND_ADDITIVE);
+                animation.draw(pos, self.scale, flip_h, self.flash_color, &mut d);
+            }
+        }
+    }
+}
+
+// Reads a JSON list of { type, direction, file, num_frames, speed, looping }
+// entries and adds each one to the sprite. Entries sharing a multi-row sheet
+// can also give { row, frame_height }, non-pixel-art sheets { smooth }, and
+// { events } lists frames the game reacts to.
+pub fn load_animations(
+    rl: &mut RaylibHandle, thread: &RaylibThread,
+    textures: &mut TextureManager,
+    sprite: &mut AnimatedSprite,
+    path: &str) -> Result<(), String>
+{
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Couldn't read animation manifest {}: {}", path, e))?;
+    let manifest = json::parse(&text)
+        .map_err(|e| format!("Couldn't parse animation manifest {}: {}", path, e))?;
+    let entries = manifest.as_array()
+        .ok_or(format!("Animation manifest {} should be a list", path))?;
+
+    for entry in entries {
+        let field = |name: &str| entry.get(name)
+            .ok_or(format!("Animation entry in {} is missing \"{}\"", path, name));
+
+        let direction_name = field("direction")?.as_str().unwrap_or_default();
+        let direction = Direction::from_name(direction_name)
+            .ok_or(format!("Unknown direction \"{}\" in {}", direction_name, path))?;
+        let type_name = field("type")?.as_str().unwrap_or_default();
+        let animation_type = AnimationType::from_name(type_name, direction)
+            .ok_or(format!("Unknown animation type \"{}\" in {}", type_name, path))?;
+
+        let file = field("file")?.as_str()
+            .ok_or(format!("\"file\" should be a string in {}", path))?;
+        let num_frames = field("num_frames")?.as_f64()
+            .ok_or(format!("\"num_frames\" should be a number in {}", path))?;
+        let speed = field("speed")?.as_f64()
+            .ok_or(format!("\"speed\" should be a number in {}", path))?;
+        let looping = entry.get("looping").and_then(json::Value::as_bool).unwrap_or(true);
+
+        // Pixel art is the default, "smooth" opts a sheet into bilinear filtering
+        let texture = if entry.get("smooth").and_then(json::Value::as_bool).unwrap_or(false) {
+            textures.load_with_filter(rl, thread, file, TextureFilter::TEXTURE_FILTER_BILINEAR)?
+        } else {
+            textures.load(rl, thread, file)?
+        };
+        let mut builder = SpriteAnimationBuilder::new(texture)
+            .frames(num_frames as u32)
+            .speed(speed as u32)
+            .looping(looping);
+        if let Some(row) = entry.get("row").and_then(json::Value::as_f64) {
+            let frame_height = field("frame_height")?.as_f64()
+                .ok_or(format!("\"frame_height\" should be a number in {}", path))?;
+            builder = builder.row(row as u32, frame_height as f32);
+        }
+        if let Some(events) = entry.get("events").and_then(json::Value::as_array) {
+            let frames = events.iter()
+                .map(|frame| frame.as_f64().map(|f| f as u32)
+                    .ok_or(format!("\"events\" should be a list of frame numbers in {}", path)))
+                .collect::<Result<Vec<u32>, String>>()?;
+            builder = builder.event_frames(frames);
+        }
+        if let Some(durations) = entry.get("durations").and_then(json::Value::as_array) {
+            let durations = durations.iter()
+                .map(|duration| duration.as_f64().map(|d| d as f32)
+                    .ok_or(format!("\"durations\" should be a list of seconds in {}", path)))
+                .collect::<Result<Vec<f32>, String>>()?;
+            builder = builder.frame_durations(durations);
+        }
+        sprite.add_animation(animation_type, builder.build());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::textures::blank_texture;
+
+    #[test]
+    fn speed_zero_animates_without_panicking() {
+        let mut animation = SpriteAnimation::new(blank_texture(64, 16), 4, 0, true);
+        // Clamped to one frame a second
+        animation.animate(0.5);
+        assert_eq!(animation.clock.current_frame(), 0);
+        animation.animate(0.6);
+        assert_eq!(animation.clock.current_frame(), 1);
+    }
+
+    #[test]
+    fn uneven_sheet_width_is_detected_and_rounded_down() {
+        assert!(frames_divide_evenly(96, 4));
+        assert!(!frames_divide_evenly(100, 3));
+        assert!(!frames_divide_evenly(100, 0));
+
+        let animation = SpriteAnimation::new(blank_texture(100, 16), 3, 10, true);
+        assert_eq!(animation.frame_width, 33.0);
+    }
+}
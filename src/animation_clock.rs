@@ -0,0 +1,187 @@
+// The timing half of an animation: which frame it's on and when to move to
+// the next one. Knows nothing about textures, so it works without a window.
+pub struct AnimationClock {
+    num_frames: u32,
+    current_frame: u32,
+    frame_timer: f32,
+    speed: u32, // frames per second
+    frame_durations: Vec<f32>, // seconds per frame, overriding speed for the frames it covers
+    looping: bool,
+    event_frames: Vec<u32>, // frames that report back to the caller when entered
+}
+
+impl AnimationClock {
+    pub fn new(num_frames: u32, speed: u32, looping: bool) -> AnimationClock {
+        AnimationClock {
+            num_frames: num_frames.max(1),
+            current_frame: 0,
+            frame_timer: 0.0,
+            // A speed of 0 would never advance a frame, clamp it so a typo at
+            // the call site still plays the animation (slowly) instead of
+            // breaking it.
+            speed: speed.max(1),
+            frame_durations: Vec::new(),
+            looping,
+            event_frames: Vec::new(),
+        }
+    }
+
+    pub fn set_event_frames(&mut self, frames: Vec<u32>) {
+        self.event_frames = frames;
+    }
+
+    // Seconds to hold each frame, in place of the uniform speed. Frames past
+    // the end of durations keep the speed, and like it a duration of 0 is
+    // clamped so the frame still advances.
+    pub fn set_frame_durations(&mut self, durations: Vec<f32>) {
+        self.frame_durations = durations.into_iter().map(|duration| duration.max(0.001)).collect();
+    }
+
+    fn frame_duration(&self, frame: u32) -> f32 {
+        self.frame_durations.get(frame as usize).copied().unwrap_or(1.0 / self.speed as f32)
+    }
+
+    pub fn current_frame(&self) -> u32 {
+        self.current_frame
+    }
+
+    pub fn num_frames(&self) -> u32 {
+        self.num_frames
+    }
+
+    // How far through the cycle it is, from 0 at the start of the first frame
+    // up to 1 at the end of the last, counting the time into the current one
+    pub fn progress(&self) -> f32 {
+        if self.is_finished() {
+            return 1.0;
+        }
+        let total: f32 = (0..self.num_frames).map(|frame| self.frame_duration(frame)).sum();
+        let done: f32 = (0..self.current_frame).map(|frame| self.frame_duration(frame)).sum();
+        ((done + self.frame_timer) / total).min(1.0)
+    }
+
+    pub fn reset(&mut self) {
+        self.current_frame = 0;
+        self.frame_timer = 0.0;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.current_frame == self.num_frames - 1
+    }
+
+    // Returns the event frames entered this tick, in the order they were hit
+    pub fn advance(&mut self, dt: f32) -> Vec<u32> {
+        let mut events = Vec::new();
+        if self.is_finished() {
+            return events;
+        }
+
+        self.frame_timer += dt;
+        loop {
+            let frame_time = self.frame_duration(self.current_frame);
+            if self.frame_timer < frame_time {
+                break;
+            }
+            self.frame_timer -= frame_time;
+            self.current_frame += 1;
+
+            if self.current_frame > self.num_frames - 1 {
+                if self.looping {
+                    self.current_frame = 0;
+                } else {
+                    // One-shot animations hold on their last frame
+                    self.current_frame = self.num_frames - 1;
+                    self.frame_timer = 0.0;
+                    break;
+                }
+            }
+
+            if self.event_frames.contains(&self.current_frame) {
+                events.push(self.current_frame);
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_frames_fire_once_per_entry() {
+        let mut clock = AnimationClock::new(4, 10, true);
+        clock.set_event_frames(vec![1, 3]);
+
+        // Two full cycles, a hundredth of a second at a time
+        let mut events = Vec::new();
+        for _ in 0..80 {
+            events.extend(clock.advance(0.01));
+        }
+        assert_eq!(events, vec![1, 3, 1, 3]);
+    }
+
+    #[test]
+    fn frames_skipped_in_one_step_still_fire() {
+        let mut clock = AnimationClock::new(4, 10, true);
+        clock.set_event_frames(vec![1, 2]);
+        assert_eq!(clock.advance(0.25), vec![1, 2]);
+        assert_eq!(clock.advance(0.01), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn uneven_frame_durations() {
+        let mut clock = AnimationClock::new(3, 10, true);
+        clock.set_frame_durations(vec![0.125, 0.375, 0.25]);
+        let frames: Vec<u32> = (0..8).map(|_| {
+            clock.advance(0.125);
+            clock.current_frame()
+        }).collect();
+        assert_eq!(frames, vec![1, 1, 1, 2, 2, 0, 1, 1]);
+    }
+
+    #[test]
+    fn frames_without_a_duration_keep_the_speed() {
+        let mut clock = AnimationClock::new(3, 4, true);
+        clock.set_frame_durations(vec![0.5]);
+        clock.advance(0.5);
+        assert_eq!(clock.current_frame(), 1);
+        clock.advance(0.25);
+        assert_eq!(clock.current_frame(), 2);
+    }
+
+    #[test]
+    fn looping_wraps_back_to_the_first_frame() {
+        let mut clock = AnimationClock::new(4, 10, true);
+        clock.advance(0.35);
+        assert_eq!(clock.current_frame(), 3);
+        clock.advance(0.1);
+        assert_eq!(clock.current_frame(), 0);
+        assert!(!clock.is_finished());
+    }
+
+    #[test]
+    fn one_shot_holds_its_last_frame() {
+        let mut clock = AnimationClock::new(4, 10, false);
+        clock.set_event_frames(vec![3]);
+        clock.advance(0.25);
+        assert!(!clock.is_finished());
+        assert_eq!(clock.advance(0.1), vec![3]);
+        assert!(clock.is_finished());
+        assert_eq!(clock.progress(), 1.0);
+
+        // Held there however long it keeps being advanced, and the last
+        // frame's event doesn't fire again
+        assert_eq!(clock.advance(5.0), Vec::<u32>::new());
+        assert_eq!(clock.current_frame(), 3);
+    }
+
+    #[test]
+    fn speed_zero_still_advances() {
+        let mut clock = AnimationClock::new(2, 0, true);
+        clock.advance(0.5);
+        assert_eq!(clock.current_frame(), 0);
+        clock.advance(0.5);
+        assert_eq!(clock.current_frame(), 1);
+    }
+}
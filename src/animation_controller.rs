@@ -0,0 +1,112 @@
+use crate::animation::{AnimationType, Direction};
+
+// What a character is doing as far as its animation is concerned, in order
+// of priority: dying beats attacking, which beats running, which beats
+// standing still
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum AnimationState {
+    Dead,
+    Attacking,
+    Running,
+    Idle,
+}
+
+impl AnimationState {
+    fn of(animation_type: AnimationType) -> AnimationState {
+        match animation_type {
+            AnimationType::Idle(_) => Self::Idle,
+            AnimationType::Run(_) => Self::Running,
+            AnimationType::Attack1(_) | AnimationType::Attack2(_) => Self::Attacking,
+            AnimationType::Death(_) => Self::Dead,
+        }
+    }
+
+    // Ones that play through once and hold until they're done
+    fn is_locking(self) -> bool {
+        matches!(self, Self::Dead | Self::Attacking)
+    }
+}
+
+// Decides which animation plays from what the character asks for, so only
+// allowed transitions happen. Idle and running switch freely. An attack
+// can't be cut short by anything but death until finish is called, and
+// death holds until force brings the character back.
+pub struct AnimationController {
+    state: AnimationState,
+    direction: Direction,
+}
+
+impl AnimationController {
+    pub fn new(direction: Direction) -> AnimationController {
+        AnimationController { state: AnimationState::Idle, direction }
+    }
+
+    pub fn state(&self) -> AnimationState {
+        self.state
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.state.is_locking()
+    }
+
+    // Ignored while locked unless state outranks the current one, returns
+    // whether it was taken
+    pub fn request(&mut self, state: AnimationState, direction: Direction) -> bool {
+        if self.is_locked() && state >= self.state {
+            return false;
+        }
+        self.state = state;
+        self.direction = direction;
+        true
+    }
+
+    // The locked animation has played out, an attack goes back to idle
+    pub fn finish(&mut self) {
+        if self.state == AnimationState::Attacking {
+            self.state = AnimationState::Idle;
+        }
+    }
+
+    // Skips the rules, for reviving and loading a save
+    pub fn force(&mut self, animation_type: AnimationType) {
+        self.state = AnimationState::of(animation_type);
+        self.direction = animation_type.direction();
+    }
+
+    pub fn animation(&self) -> AnimationType {
+        match self.state {
+            AnimationState::Dead => AnimationType::Death(self.direction),
+            AnimationState::Attacking => AnimationType::Attack1(self.direction),
+            AnimationState::Running => AnimationType::Run(self.direction),
+            AnimationState::Idle => AnimationType::Idle(self.direction),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn movement_cant_cancel_an_attack() {
+        let mut controller = AnimationController::new(Direction::Down);
+        assert!(controller.request(AnimationState::Attacking, Direction::Right));
+        assert!(!controller.request(AnimationState::Running, Direction::Left));
+        assert!(!controller.request(AnimationState::Idle, Direction::Up));
+        assert_eq!(controller.animation(), AnimationType::Attack1(Direction::Right));
+
+        controller.finish();
+        assert!(controller.request(AnimationState::Running, Direction::Left));
+        assert_eq!(controller.animation(), AnimationType::Run(Direction::Left));
+    }
+
+    #[test]
+    fn death_cuts_an_attack_short_and_holds() {
+        let mut controller = AnimationController::new(Direction::Down);
+        controller.request(AnimationState::Attacking, Direction::Down);
+        assert!(controller.request(AnimationState::Dead, Direction::Down));
+        controller.finish();
+        assert!(!controller.request(AnimationState::Idle, Direction::Down));
+        assert_eq!(controller.state(), AnimationState::Dead);
+    }
+}
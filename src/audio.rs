@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use raylib::prelude::*;
+
+// Sound effects keyed by name plus one music track and one ambient loop
+// under it. They all borrow the audio device, so the device can only be closed (by dropping the RaylibAudio) once
+// this is gone.
+pub struct AudioManager<'aud> {
+    audio: &'aud RaylibAudio,
+    sounds: HashMap<String, Sound<'aud>>,
+    music: Option<Music<'aud>>,
+    ambience: Option<Music<'aud>>,
+    music_volume: f32,
+    muted: bool,
+}
+
+impl<'aud> AudioManager<'aud> {
+    pub fn new(audio: &'aud RaylibAudio) -> AudioManager<'aud> {
+        AudioManager {
+            audio,
+            sounds: HashMap::new(),
+            music: None,
+            ambience: None,
+            music_volume: 1.0,
+            muted: false,
+        }
+    }
+
+    pub fn load(&mut self, name: &str, path: &str) -> Result<(), String> {
+        let sound = self.audio.new_sound(path)
+            .map_err(|e| format!("Couldn't load sound {}: {}", path, e))?;
+        self.sounds.insert(name.to_string(), sound);
+        Ok(())
+    }
+
+    // Doesn't restart a sound that's still playing, so repeated triggers
+    // can't pile up on top of each other
+    pub fn play(&self, name: &str) {
+        match self.sounds.get(name) {
+            Some(sound) if !sound.is_playing() => sound.play(),
+            Some(_) => {}
+            None => eprintln!("WARNING: no sound named {}", name),
+        }
+    }
+
+    // Replaces the current track, music streams loop by default
+    pub fn play_music(&mut self, path: &str) -> Result<(), String> {
+        let music = self.audio.new_music(path)
+            .map_err(|e| format!("Couldn't load music {}: {}", path, e))?;
+        music.play_stream();
+        self.music = Some(music);
+        self.apply_music_volume();
+        Ok(())
+    }
+
+    // Like the music, but played alongside it. None stops what was playing.
+    pub fn play_ambience(&mut self, path: Option<&str>) -> Result<(), String> {
+        self.ambience = path.map(|path| self.audio.new_music(path)
+            .map_err(|e| format!("Couldn't load ambience {}: {}", path, e)))
+            .transpose()?;
+        if let Some(ambience) = &self.ambience {
+            ambience.play_stream();
+        }
+        self.apply_music_volume();
+        Ok(())
+    }
+
+    fn streams(&self) -> impl Iterator<Item = &Music<'aud>> {
+        self.music.iter().chain(&self.ambience)
+    }
+
+    // Streams have to be refilled every frame or they stutter
+    pub fn update_music(&self) {
+        for stream in self.streams() {
+            stream.update_stream();
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.music_volume = volume.clamp(0.0, 1.0);
+        self.apply_music_volume();
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        self.apply_music_volume();
+    }
+
+    fn apply_music_volume(&self) {
+        for stream in self.streams() {
+            stream.set_volume(if self.muted { 0.0 } else { self.music_volume });
+        }
+    }
+
+    pub fn pause_music(&self) {
+        for stream in self.streams() {
+            stream.pause_stream();
+        }
+    }
+
+    pub fn resume_music(&self) {
+        for stream in self.streams() {
+            stream.resume_stream();
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use raylib::prelude::*;
+
+use crate::rng::Rng;
+
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 1.0;
+const FRAME_MARGIN: f32 = 160.0; // screen pixels kept clear around a framed area
+const MAX_SHAKE: f32 = 12.0; // pixels of jitter, however many shakes pile up
+
+// camera.target is what's drawn: the smoothed follow position plus any shake
+pub struct FollowCamera {
+    pub camera: Camera2D,
+    pub smoothing: f32, // higher catches up faster
+    follow_target: Vector2,
+    shake_intensity: f32, // pixels of jitter at the start of the shake
+    shake_duration: f32,
+    shake_timer: f32,
+    rng: Rng,
+}
+
+impl FollowCamera {
+    pub fn new(target: Vector2, screen_width: f32, screen_height: f32, zoom: f32) -> FollowCamera {
+        FollowCamera {
+            camera: Camera2D {
+                offset: Vector2::new(screen_width / 2.0, screen_height / 2.0),
+                target,
+                rotation: 0.0,
+                zoom,
+            },
+            smoothing: 8.0,
+            follow_target: target,
+            shake_intensity: 0.0,
+            shake_duration: 0.0,
+            shake_timer: 0.0,
+            rng: Rng::new(0x85EBCA6B),
+        }
+    }
+
+    pub fn follow(&mut self, target: Vector2, dt: f32) {
+        // Exponential smoothing so the catch-up speed doesn't depend on frame rate
+        let t = 1.0 - (-self.smoothing * dt).exp();
+        self.follow_target = self.follow_target.lerp(target, t);
+
+        self.shake_timer = (self.shake_timer - dt).max(0.0);
+        let magnitude = self.shake_magnitude();
+        let jitter = Vector2::new(self.rng.range_f32(-1.0, 1.0), self.rng.range_f32(-1.0, 1.0));
+        self.camera.target = self.follow_target + jitter * magnitude;
+    }
+
+    // Follows the middle of area, zooming out as far as MIN_ZOOM to keep all
+    // of it on screen. The screen size comes from the offset, which is its
+    // middle.
+    pub fn frame(&mut self, area: Rectangle, dt: f32) {
+        let screen = self.camera.offset * 2.0;
+        let fit = |screen: f32, size: f32| if size > 0.0 { (screen - FRAME_MARGIN).max(0.0) / size } else { MAX_ZOOM };
+        let zoom = fit(screen.x, area.width).min(fit(screen.y, area.height)).clamp(MIN_ZOOM, MAX_ZOOM);
+        let t = 1.0 - (-self.smoothing * dt).exp();
+        self.camera.zoom += (zoom - self.camera.zoom) * t;
+        self.follow(Vector2::new(area.x + area.width / 2.0, area.y + area.height / 2.0), dt);
+    }
+
+    // Jumps straight to target, dropping any smoothing or shake in progress
+    pub fn snap_to(&mut self, target: Vector2) {
+        self.follow_target = target;
+        self.camera.target = target;
+        self.shake_timer = 0.0;
+    }
+
+    // Jitters the view by up to intensity pixels, fading linearly to nothing
+    // over duration seconds. A shake on top of one still going adds to what's
+    // left of it, up to MAX_SHAKE, and lasts as long as the longer of the two.
+    pub fn trigger_shake(&mut self, intensity: f32, duration: f32) {
+        if duration <= 0.0 || intensity <= 0.0 {
+            return;
+        }
+        self.shake_intensity = (self.shake_magnitude() + intensity).min(MAX_SHAKE);
+        self.shake_duration = duration.max(self.shake_timer);
+        self.shake_timer = self.shake_duration;
+    }
+
+    pub fn shake_magnitude(&self) -> f32 {
+        if self.shake_timer <= 0.0 {
+            return 0.0;
+        }
+        self.shake_intensity * self.shake_timer / self.shake_duration
+    }
+}
+
+// World-space area a camera shows on a screen of the given size. Rotation is
+// ignored, which is fine while nothing rotates the camera.
+pub fn camera_view(camera: &Camera2D, screen_width: f32, screen_height: f32) -> Rectangle {
+    Rectangle::new(
+        camera.target.x - camera.offset.x / camera.zoom,
+        camera.target.y - camera.offset.y / camera.zoom,
+        screen_width / camera.zoom,
+        screen_height / camera.zoom,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn still_camera() -> FollowCamera {
+        FollowCamera::new(Vector2::new(100.0, 100.0), 640.0, 480.0, 1.0)
+    }
+
+    #[test]
+    fn shake_decays_to_exact_following() {
+        let mut camera = still_camera();
+        let target = Vector2::new(100.0, 100.0);
+        camera.trigger_shake(6.0, 0.25);
+
+        let mut last = camera.shake_magnitude();
+        for _ in 0..20 {
+            camera.follow(target, 1.0 / 60.0);
+            let magnitude = camera.shake_magnitude();
+            assert!(magnitude <= last);
+            last = magnitude;
+        }
+        assert_eq!(camera.shake_magnitude(), 0.0);
+        assert_eq!(camera.camera.target, target);
+    }
+
+    #[test]
+    fn shakes_build_up_to_the_cap() {
+        let mut camera = still_camera();
+        camera.trigger_shake(4.0, 0.5);
+        camera.trigger_shake(4.0, 0.2);
+        assert_eq!(camera.shake_magnitude(), 8.0);
+
+        for _ in 0..10 {
+            camera.trigger_shake(6.0, 0.25);
+        }
+        assert_eq!(camera.shake_magnitude(), MAX_SHAKE);
+    }
+}
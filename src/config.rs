@@ -0,0 +1,175 @@
+use crate::minimap::ScreenCorner;
+use crate::screen::{ScalingMode, VIRTUAL_HEIGHT};
+use crate::weather::WeatherKind;
+
+// Window and graphics settings from config.toml. Only the flat key = value subset of TOML
+// the file needs: strings, integers, booleans and # comments.
+
+const CONFIG_FILE: &str = "config.toml";
+pub const MAX_PLAYERS: usize = 2; // there are only bindings for two on one keyboard
+
+pub struct Config {
+    pub width: i32,
+    pub height: i32,
+    pub title: String,
+    pub target_fps: u32,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub scaling: ScalingMode, // how the game is blown up to the window
+    pub lighting: bool, // the darkness and light around the player
+    pub players: usize, // local co-op, up to MAX_PLAYERS
+    pub split_screen: bool, // a view per player instead of one camera framing them all
+    pub mouse_aim: bool, // the first player faces and shoots towards the cursor
+    pub player_speed: Option<f32>, // pixels per second, for tuning how movement feels
+    pub player_acceleration: Option<f32>, // pixels per second per second
+    pub player_friction: Option<f32>, // the same, slowing down once nothing's pressed
+    pub seed: Option<u32>, // for the gameplay randomness, from the clock when left out
+    pub hot_reload: bool, // for working on the art: sprite sheets load again once a second when they change
+    pub minimap: bool, // shown when the game starts, it can be toggled either way
+    pub minimap_size: i32, // virtual screen pixels across
+    pub minimap_corner: ScreenCorner,
+    pub weather: Option<WeatherKind>, // in place of what falls on the levels that have weather, theirs when left out
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            width: 640,
+            height: 480,
+            title: "Non-Hot Reloaded Game".to_string(),
+            target_fps: 60,
+            fullscreen: false,
+            vsync: false,
+            scaling: ScalingMode::Integer,
+            lighting: true,
+            players: 1,
+            split_screen: false,
+            mouse_aim: true,
+            player_speed: None,
+            player_acceleration: None,
+            player_friction: None,
+            seed: None,
+            hot_reload: false,
+            minimap: true,
+            minimap_size: 100,
+            minimap_corner: ScreenCorner::BottomRight,
+            weather: None,
+        }
+    }
+}
+
+// For speeds and rates, which only make sense above 0
+fn positive(n: i64) -> Option<f32> {
+    (n > 0).then_some(n as f32)
+}
+
+enum ConfigValue {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+}
+
+// Parses the right of an =, dropping a trailing comment but not a # inside
+// a quoted string
+fn parse_value(text: &str) -> Result<ConfigValue, String> {
+    let text = text.trim();
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.chars();
+        loop {
+            match chars.next() {
+                Some('"') => break,
+                Some('\\') => match chars.next() {
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    other => return Err(format!("unsupported escape \\{}", other.map(String::from).unwrap_or_default())),
+                },
+                Some(c) => value.push(c),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+        let rest = chars.as_str().trim();
+        if !rest.is_empty() && !rest.starts_with('#') {
+            return Err(format!("unexpected {} after string", rest));
+        }
+        return Ok(ConfigValue::String(value));
+    }
+
+    let text = text.split('#').next().unwrap_or_default().trim();
+    match text {
+        "true" => Ok(ConfigValue::Bool(true)),
+        "false" => Ok(ConfigValue::Bool(false)),
+        _ => text.replace('_', "").parse().map(ConfigValue::Integer)
+            .map_err(|_| format!("expected a string, integer or boolean, got {}", text)),
+    }
+}
+
+impl Config {
+    // A missing file means defaults, a broken one is reported and also
+    // means defaults, so the game always gets a window
+    pub fn load() -> Config {
+        let text = match std::fs::read_to_string(CONFIG_FILE) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Config::default(),
+            Err(e) => {
+                eprintln!("WARNING: Couldn't read {}: {}, using default settings", CONFIG_FILE, e);
+                return Config::default();
+            }
+        };
+        match Config::parse(&text) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("WARNING: Couldn't parse {}: {}, using default settings", CONFIG_FILE, e);
+                Config::default()
+            }
+        }
+    }
+
+    fn parse(text: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line.split_once('=')
+                .ok_or(format!("line {}: expected key = value", i + 1))?;
+            let key = key.trim();
+            let value = parse_value(value).map_err(|e| format!("line {}: {}", i + 1, e))?;
+            let wrong_type = || format!("line {}: wrong type for {}", i + 1, key);
+            let out_of_range = || format!("line {}: {} is out of range", i + 1, key);
+            match (key, value) {
+                ("width", ConfigValue::Integer(n)) => config.width = i32::try_from(n).ok().filter(|n| *n > 0).ok_or_else(out_of_range)?,
+                ("height", ConfigValue::Integer(n)) => config.height = i32::try_from(n).ok().filter(|n| *n > 0).ok_or_else(out_of_range)?,
+                ("target_fps", ConfigValue::Integer(n)) => config.target_fps = u32::try_from(n).map_err(|_| out_of_range())?,
+                ("title", ConfigValue::String(s)) => config.title = s,
+                ("fullscreen", ConfigValue::Bool(b)) => config.fullscreen = b,
+                ("vsync", ConfigValue::Bool(b)) => config.vsync = b,
+                ("scaling", ConfigValue::String(s)) => config.scaling = ScalingMode::from_name(&s)
+                    .ok_or(format!("line {}: scaling must be integer, fit or stretch", i + 1))?,
+                ("lighting", ConfigValue::Bool(b)) => config.lighting = b,
+                ("players", ConfigValue::Integer(n)) => config.players = usize::try_from(n).ok().filter(|n| (1..=MAX_PLAYERS).contains(n)).ok_or_else(out_of_range)?,
+                ("split_screen", ConfigValue::Bool(b)) => config.split_screen = b,
+                ("mouse_aim", ConfigValue::Bool(b)) => config.mouse_aim = b,
+                ("player_speed", ConfigValue::Integer(n)) => config.player_speed = Some(positive(n).ok_or_else(out_of_range)?),
+                ("player_acceleration", ConfigValue::Integer(n)) => config.player_acceleration = Some(positive(n).ok_or_else(out_of_range)?),
+                ("player_friction", ConfigValue::Integer(n)) => config.player_friction = Some(positive(n).ok_or_else(out_of_range)?),
+                ("seed", ConfigValue::Integer(n)) => config.seed = Some(u32::try_from(n).map_err(|_| out_of_range())?),
+                ("hot_reload", ConfigValue::Bool(b)) => config.hot_reload = b,
+                ("minimap", ConfigValue::Bool(b)) => config.minimap = b,
+                ("minimap_size", ConfigValue::Integer(n)) => config.minimap_size = i32::try_from(n).ok().filter(|n| (1..=VIRTUAL_HEIGHT).contains(n)).ok_or_else(out_of_range)?,
+                ("minimap_corner", ConfigValue::String(s)) => config.minimap_corner = ScreenCorner::from_name(&s)
+                    .ok_or(format!("line {}: minimap_corner must be top_left, top_right, bottom_left or bottom_right", i + 1))?,
+                ("weather", ConfigValue::String(s)) => config.weather = Some(WeatherKind::from_name(&s)
+                    .ok_or(format!("line {}: weather must be rain or snow", i + 1))?),
+                ("width" | "height" | "target_fps" | "title" | "fullscreen" | "vsync" | "scaling" | "lighting" | "players" | "split_screen" | "mouse_aim" | "player_speed" | "player_acceleration" | "player_friction" | "seed" | "hot_reload"
+                    | "minimap" | "minimap_size" | "minimap_corner" | "weather", _) => return Err(wrong_type()),
+                _ => return Err(format!("line {}: unknown setting {}", i + 1, key)),
+            }
+        }
+        Ok(config)
+    }
+}
@@ -0,0 +1,73 @@
+// Everything a player can only do every so often
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum AbilityId {
+    Dash,
+    Attack,
+    Fire,
+}
+
+impl AbilityId {
+    const COUNT: usize = 3;
+
+    fn index(self) -> usize {
+        match self {
+            Self::Dash => 0,
+            Self::Attack => 1,
+            Self::Fire => 2,
+        }
+    }
+}
+
+// When each ability can be used again, one slot per ability so nothing is
+// ever allocated. Times are the game clock's elapsed seconds, see GameClock.
+#[derive(Default)]
+pub struct Cooldowns {
+    ready_at: [f32; AbilityId::COUNT],
+    now: f32,
+}
+
+impl Cooldowns {
+    pub fn new() -> Cooldowns {
+        Cooldowns::default()
+    }
+
+    pub fn ready(&self, id: AbilityId) -> bool {
+        self.now >= self.ready_at[id.index()]
+    }
+
+    // Starts over even if it was still cooling down
+    pub fn trigger(&mut self, id: AbilityId, duration: f32) {
+        self.ready_at[id.index()] = self.now + duration;
+    }
+
+    // Catches up with the game clock's elapsed seconds
+    pub fn set_time(&mut self, elapsed: f32) {
+        self.now = elapsed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn not_ready_until_the_duration_is_over() {
+        let mut cooldowns = Cooldowns::new();
+        assert!(cooldowns.ready(AbilityId::Dash));
+        cooldowns.trigger(AbilityId::Dash, 0.5);
+        assert!(!cooldowns.ready(AbilityId::Dash));
+
+        cooldowns.set_time(0.3);
+        assert!(!cooldowns.ready(AbilityId::Dash));
+        cooldowns.set_time(0.5);
+        assert!(cooldowns.ready(AbilityId::Dash));
+    }
+
+    #[test]
+    fn abilities_cool_down_separately() {
+        let mut cooldowns = Cooldowns::new();
+        cooldowns.trigger(AbilityId::Attack, 1.0);
+        assert!(cooldowns.ready(AbilityId::Fire));
+        assert!(cooldowns.ready(AbilityId::Dash));
+    }
+}
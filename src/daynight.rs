@@ -0,0 +1,111 @@
+use raylib::prelude::*;
+
+use crate::screen::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+use crate::world::GameClock;
+
+// Seconds of real time for one full day at a speed of 1
+const DAY_LENGTH: f32 = 240.0;
+
+// Overlay colors keyed by time of day, 0 being dawn. Times must be sorted
+// and the cycle wraps from the last key back to the first.
+const GRADIENT: [(f32, Color); 5] = [
+    (0.0, Color::new(255, 170, 120, 50)),  // dawn
+    (0.15, Color::new(255, 255, 255, 0)),  // day
+    (0.45, Color::new(255, 140, 60, 70)),  // dusk
+    (0.6, Color::new(20, 20, 70, 150)),    // night
+    (0.9, Color::new(20, 20, 70, 150)),
+];
+
+// Tints the world with a translucent rectangle that follows the game clock,
+// so it stands still whenever that does and a loaded save picks the time of
+// day back up from the clock it brings
+pub struct DayNightCycle {
+    pub time_of_day: f32, // [0, 1)
+    pub speed: f32, // multiplier on DAY_LENGTH
+    pub paused: bool,
+    start: f32, // the time of day when the clock read 0
+    pub gradient: Vec<(f32, Color)>,
+}
+
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    Color::new(channel(a.r, b.r), channel(a.g, b.g), channel(a.b, b.b), channel(a.a, b.a))
+}
+
+impl DayNightCycle {
+    pub fn new(time_of_day: f32) -> DayNightCycle {
+        DayNightCycle {
+            time_of_day: time_of_day.rem_euclid(1.0),
+            speed: 1.0,
+            paused: false,
+            start: time_of_day.rem_euclid(1.0),
+            gradient: GRADIENT.to_vec(),
+        }
+    }
+
+    // Paused, it holds the time it's at and carries on from there afterwards
+    pub fn update(&mut self, clock: &GameClock) {
+        let days = clock.elapsed * self.speed / DAY_LENGTH;
+        if self.paused {
+            self.start = (self.time_of_day - days).rem_euclid(1.0);
+        }
+        self.time_of_day = (self.start + days).rem_euclid(1.0);
+    }
+
+    pub fn tint(&self) -> Color {
+        let (Some(&first), Some(&last)) = (self.gradient.first(), self.gradient.last()) else {
+            return Color::BLANK;
+        };
+
+        let t = self.time_of_day;
+        let (from, to) = match self.gradient.iter().position(|&(key, _)| key > t) {
+            Some(0) | None => (last, (first.0 + 1.0, first.1)), // wrapping around midnight
+            Some(i) => (self.gradient[i - 1], self.gradient[i]),
+        };
+        let t = if t < from.0 { t + 1.0 } else { t };
+        let span = to.0 - from.0;
+        let amount = if span > 0.0 { (t - from.0) / span } else { 0.0 };
+        lerp_color(from.1, to.1, amount.clamp(0.0, 1.0))
+    }
+
+    // Screen space, after the world and before the HUD
+    pub fn draw(&self, d: &mut RaylibDrawHandle) {
+        let tint = self.tint();
+        if tint.a > 0 {
+            d.draw_rectangle(0, 0, VIRTUAL_WIDTH, VIRTUAL_HEIGHT, tint);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(elapsed: f32) -> GameClock {
+        GameClock { tick: 0, elapsed }
+    }
+
+    #[test]
+    fn follows_the_clock() {
+        let mut cycle = DayNightCycle::new(0.1);
+        cycle.update(&at(DAY_LENGTH / 4.0));
+        assert!((cycle.time_of_day - 0.35).abs() < 0.0001);
+
+        // A clock from a save puts it straight at that time, wrapping past midnight
+        cycle.update(&at(DAY_LENGTH * 2.0 + DAY_LENGTH * 0.95));
+        assert!((cycle.time_of_day - 0.05).abs() < 0.0001);
+    }
+
+    #[test]
+    fn pausing_holds_the_time_and_carries_on_from_it() {
+        let mut cycle = DayNightCycle::new(0.0);
+        cycle.update(&at(DAY_LENGTH / 2.0));
+        cycle.paused = true;
+        cycle.update(&at(DAY_LENGTH * 0.75));
+        assert!((cycle.time_of_day - 0.5).abs() < 0.0001);
+
+        cycle.paused = false;
+        cycle.update(&at(DAY_LENGTH * 0.85));
+        assert!((cycle.time_of_day - 0.6).abs() < 0.0001);
+    }
+}
@@ -0,0 +1,139 @@
+use raylib::prelude::*;
+
+use crate::screen::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
+const FONT_SIZE: i32 = 20;
+const LINE_HEIGHT: i32 = 24;
+const LINES_PER_PAGE: usize = 3;
+const PADDING: i32 = 12;
+const MARGIN: i32 = 20; // between the box and the edges of the screen
+const CHARS_PER_SECOND: f32 = 40.0;
+
+// Greedy word wrap, a word wider than the whole line is split wherever it
+// runs out of room. Newlines in the text always start a new line.
+fn wrap(rl: &RaylibHandle, text: &str, width: i32) -> Vec<String> {
+    let fits = |line: &str| rl.measure_text(line, FONT_SIZE) <= width;
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+            if fits(&candidate) {
+                line = candidate;
+                continue;
+            }
+
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            for c in word.chars() {
+                line.push(c);
+                if !fits(&line) && line.chars().count() > 1 {
+                    line.pop();
+                    lines.push(std::mem::replace(&mut line, c.to_string()));
+                }
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// A panel along the bottom of the screen that types its text out a
+// character at a time, a page of a few lines at once
+pub struct DialogueBox {
+    pages: Vec<Vec<String>>,
+    page: usize,
+    revealed: f32, // characters of the current page shown so far
+}
+
+impl DialogueBox {
+    pub fn new() -> DialogueBox {
+        DialogueBox { pages: Vec::new(), page: 0, revealed: 0.0 }
+    }
+
+    fn panel() -> Rectangle {
+        let height = LINES_PER_PAGE as i32 * LINE_HEIGHT + PADDING * 2;
+        Rectangle::new(
+            MARGIN as f32,
+            (VIRTUAL_HEIGHT - MARGIN - height) as f32,
+            (VIRTUAL_WIDTH - MARGIN * 2) as f32,
+            height as f32,
+        )
+    }
+
+    // Measuring the text for wrapping needs the window's font, hence rl
+    pub fn open(&mut self, rl: &RaylibHandle, text: &str) {
+        let width = Self::panel().width as i32 - PADDING * 2;
+        let lines = wrap(rl, text, width);
+        self.pages = lines.chunks(LINES_PER_PAGE).map(<[String]>::to_vec).collect();
+        self.page = 0;
+        self.revealed = 0.0;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.page < self.pages.len()
+    }
+
+    fn page_length(&self) -> usize {
+        self.pages.get(self.page).map_or(0, |page| page.iter().map(|line| line.chars().count()).sum())
+    }
+
+    fn page_done(&self) -> bool {
+        self.revealed as usize >= self.page_length()
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if self.is_open() && !self.page_done() {
+            self.revealed += CHARS_PER_SECOND * dt;
+        }
+    }
+
+    // The key press: finishes typing the page if it's still going, otherwise
+    // moves to the next one, closing after the last
+    pub fn advance(&mut self) {
+        if !self.is_open() {
+            return;
+        }
+
+        if self.page_done() {
+            self.page += 1;
+            self.revealed = 0.0;
+        } else {
+            self.revealed = self.page_length() as f32;
+        }
+    }
+
+    // Screen space
+    pub fn draw(&self, d: &mut RaylibDrawHandle) {
+        let Some(page) = self.pages.get(self.page) else {
+            return;
+        };
+
+        let panel = Self::panel();
+        d.draw_rectangle_rec(panel, Color::BLACK.alpha(0.85));
+        d.draw_rectangle_lines_ex(panel, 2.0, Color::WHITE);
+
+        let mut remaining = self.revealed as usize;
+        for (i, line) in page.iter().enumerate() {
+            if remaining == 0 {
+                break;
+            }
+            let shown: String = line.chars().take(remaining).collect();
+            remaining = remaining.saturating_sub(line.chars().count());
+            let y = panel.y as i32 + PADDING + i as i32 * LINE_HEIGHT;
+            d.draw_text(&shown, panel.x as i32 + PADDING, y, FONT_SIZE, Color::WHITE);
+        }
+
+        // Waiting for the key press
+        if self.page_done() {
+            let corner = Vector2::new(panel.x + panel.width - PADDING as f32, panel.y + panel.height - PADDING as f32);
+            d.draw_triangle(
+                corner - Vector2::new(10.0, 8.0),
+                corner - Vector2::new(5.0, 0.0),
+                corner - Vector2::new(0.0, 8.0),
+                Color::WHITE,
+            );
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use raylib::prelude::*;
+
+// Anything drawn in the world that should overlap by depth: whatever stands
+// lower on screen is nearer the viewer and goes on top
+pub trait Drawable {
+    // World y of the bottom of the sprite, where it meets the ground
+    fn depth(&self) -> f32;
+    fn render(&self, d: &mut RaylibDrawHandle, view: Rectangle, alpha: f32);
+}
+
+// Back to front. The sort is stable, so equal depths keep the order given.
+pub fn draw_by_depth(drawables: &mut [&dyn Drawable], d: &mut RaylibDrawHandle, view: Rectangle, alpha: f32) {
+    drawables.sort_by(|a, b| a.depth().total_cmp(&b.depth()));
+    for drawable in drawables {
+        drawable.render(d, view, alpha);
+    }
+}
@@ -0,0 +1,486 @@
+use raylib::prelude::*;
+
+use crate::animation::{AnimatedSprite, AnimationType, Direction, direction_from_vector};
+use crate::drawable::Drawable;
+use crate::object::GameObject;
+use crate::physics::{CollisionFilter, CollisionLayer, Knockback, move_and_collide};
+use crate::world::World;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum EnemyState {
+    Idle, // waiting at a patrol point
+    Patrol, // walking to the other patrol point
+    Chase, // following a path to the player
+    Attack, // swinging, standing still until it finishes
+    Return, // walking back to the patrol point after losing the player
+}
+
+// What a spawn point puts there. They share the one sprite, only how fast
+// they are and how much they take to go down differ.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum EnemyKind {
+    Grunt,
+    Scout, // quick on its feet, but one hit is enough
+}
+
+impl EnemyKind {
+    pub fn from_name(name: &str) -> Option<EnemyKind> {
+        match name {
+            "grunt" => Some(Self::Grunt),
+            "scout" => Some(Self::Scout),
+            _ => None,
+        }
+    }
+
+    pub fn speed(self) -> f32 {
+        match self {
+            Self::Grunt => 90.0,
+            Self::Scout => 140.0,
+        }
+    }
+
+    pub fn health(self) -> i32 {
+        match self {
+            Self::Grunt => MAX_HEALTH,
+            Self::Scout => 1,
+        }
+    }
+}
+
+// What the enemy knows when deciding what to do next
+struct Senses {
+    target_distance: Option<f32>, // None with nobody to chase
+    target_visible: bool,
+    unseen_time: f32, // seconds the target has been out of sight
+    state_time: f32, // seconds in the current state
+    at_patrol_point: bool,
+    home: bool, // back where Return was heading
+    attack_ready: bool, // the cooldown since the last swing is over
+    attack_done: bool,
+}
+
+// Every transition in one place. A player has to be seen close by to be
+// noticed, after that they only get away by going far enough or staying out
+// of sight for long enough.
+fn next_state(state: EnemyState, senses: &Senses) -> EnemyState {
+    let distance = senses.target_distance;
+    let notices = senses.target_visible && distance.is_some_and(|d| d <= CHASE_RANGE);
+    let escaped = distance.is_none_or(|d| d > GIVE_UP_RANGE) || senses.unseen_time > LOSE_SIGHT_TIME;
+    let in_reach = senses.target_visible && distance.is_some_and(|d| d <= ATTACK_RANGE);
+
+    match state {
+        EnemyState::Idle | EnemyState::Patrol | EnemyState::Return if notices => EnemyState::Chase,
+        EnemyState::Idle if senses.state_time >= IDLE_TIME => EnemyState::Patrol,
+        EnemyState::Patrol if senses.at_patrol_point => EnemyState::Idle,
+        EnemyState::Chase if escaped => EnemyState::Return,
+        EnemyState::Chase if in_reach && senses.attack_ready => EnemyState::Attack,
+        EnemyState::Attack if senses.attack_done => EnemyState::Chase,
+        EnemyState::Return if senses.home => EnemyState::Patrol,
+        state => state,
+    }
+}
+
+// Walks back and forth between two points until it sees a player close by,
+// then chases and swings at them, walking back once they get away
+pub struct Enemy {
+    pub pos: Vector2,
+    pub patrol: [Vector2; 2],
+    target: usize, // index into patrol
+    blocked: bool, // the last patrol step got nowhere, which counts as arriving
+    pub chase_target: Option<Vector2>, // the nearest player, set by the game each step
+    pub target_visible: bool, // nothing solid between the enemy and chase_target
+    state: EnemyState,
+    state_timer: f32,
+    unseen_timer: f32, // seconds the chased player has been out of sight
+    attack_cooldown: f32,
+    hitbox_active: bool, // from the attack's event frame until the swing ends
+    path: Option<Vec<Vector2>>, // waypoints left, None when there's no way there
+    path_goal: Option<(usize, usize)>, // the tile path leads to, replanned when that changes
+    replan_timer: f32,
+    pub speed: f32, // pixels per second
+    pub sprite: AnimatedSprite,
+    hitbox: Rectangle, // relative to the sprite frame, in unscaled sprite pixels
+    pub health: i32,
+    pub last_hit_by: Option<(usize, u32)>, // which player's swing last landed, see Player::swing
+    knockback: Knockback,
+    pub filter: CollisionFilter,
+}
+
+// Enemies pass through each other, only walls, players and their shots meet them
+pub const ENEMY_FILTER: CollisionFilter = CollisionFilter {
+    layer: CollisionLayer::ENEMY,
+    mask: CollisionLayer::WALL.union(CollisionLayer::PLAYER).union(CollisionLayer::PLAYER_SHOT),
+};
+
+// How close counts as having reached a patrol point
+const ARRIVE_THRESHOLD: f32 = 2.0;
+const MAX_HEALTH: i32 = 3;
+const HIT_FLASH_TIME: f32 = 0.1;
+const CHASE_RANGE: f32 = 200.0; // from the enemy's center to the player's, to notice them
+const GIVE_UP_RANGE: f32 = 320.0; // the same, to stop chasing
+const ATTACK_RANGE: f32 = 36.0; // the same, to start a swing
+const LOSE_SIGHT_TIME: f32 = 3.0; // seconds out of sight before it stops chasing
+const IDLE_TIME: f32 = 1.0; // seconds waiting at each patrol point
+const ATTACK_COOLDOWN: f32 = 1.0; // seconds after a swing before the next
+const ATTACK_REACH: f32 = 16.0; // in unscaled sprite pixels
+const REPLAN_INTERVAL: f32 = 0.5; // seconds, even if the goal stays in one tile
+
+impl Enemy {
+    pub fn new(start: Vector2, end: Vector2, speed: f32, sprite: AnimatedSprite, hitbox: Rectangle) -> Enemy {
+        Enemy {
+            pos: start,
+            patrol: [start, end],
+            target: 1,
+            blocked: false,
+            chase_target: None,
+            target_visible: false,
+            state: EnemyState::Patrol,
+            state_timer: 0.0,
+            unseen_timer: 0.0,
+            attack_cooldown: 0.0,
+            hitbox_active: false,
+            path: None,
+            path_goal: None,
+            replan_timer: 0.0,
+            speed,
+            sprite,
+            hitbox,
+            health: MAX_HEALTH,
+            last_hit_by: None,
+            knockback: Knockback::default(),
+            filter: ENEMY_FILTER,
+        }
+    }
+
+    pub fn take_damage(&mut self, amount: i32) {
+        if !self.is_alive() {
+            return;
+        }
+
+        self.health = (self.health - amount).max(0);
+        self.sprite.flash(Color::WHITE, HIT_FLASH_TIME);
+        if !self.is_alive() {
+            self.hitbox_active = false;
+            self.sprite.change(AnimationType::Death(self.sprite.current().direction()));
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.health > 0
+    }
+
+    pub fn state(&self) -> EnemyState {
+        self.state
+    }
+
+    // Done once the death animation has played out, or straight away when
+    // there isn't one and the sprite fell back to something else
+    pub fn can_be_removed(&self) -> bool {
+        !self.is_alive()
+            && (self.sprite.is_finished() || !matches!(self.sprite.current(), AnimationType::Death(_)))
+    }
+
+    pub fn apply_knockback(&mut self, dir: Vector2, strength: f32) {
+        self.knockback.apply(dir, strength);
+    }
+
+    pub fn center(&self) -> Vector2 {
+        let bounds = self.bounds();
+        Vector2::new(bounds.x + bounds.width / 2.0, bounds.y + bounds.height / 2.0)
+    }
+
+    // The area in front of the enemy its swing can hit, once it connects
+    pub fn active_hitbox(&self) -> Option<Rectangle> {
+        if !self.hitbox_active {
+            return None;
+        }
+
+        let reach = ATTACK_REACH * self.sprite.scale;
+        let c = self.bounds();
+        Some(match self.sprite.current().direction().nearest_cardinal() {
+            Direction::Up => Rectangle::new(c.x, c.y - reach, c.width, reach),
+            Direction::Left => Rectangle::new(c.x - reach, c.y, reach, c.height),
+            Direction::Right => Rectangle::new(c.x + c.width, c.y, reach, c.height),
+            _ => Rectangle::new(c.x, c.y + c.height, c.width, reach),
+        })
+    }
+
+    fn senses(&self) -> Senses {
+        let swinging = matches!(self.sprite.current(), AnimationType::Attack1(_)) && !self.sprite.is_finished();
+        Senses {
+            target_distance: self.chase_target.map(|target| target.distance_to(self.center())),
+            target_visible: self.target_visible,
+            unseen_time: self.unseen_timer,
+            state_time: self.state_timer,
+            at_patrol_point: self.blocked || self.pos.distance_to(self.patrol[self.target]) <= ARRIVE_THRESHOLD,
+            home: self.patrol_goal().distance_to(self.center()) <= ARRIVE_THRESHOLD,
+            attack_ready: self.attack_cooldown <= 0.0,
+            // A missing attack animation falls back to one that never finishes
+            attack_done: !swinging,
+        }
+    }
+
+    fn enter(&mut self, state: EnemyState) {
+        match self.state {
+            EnemyState::Patrol if state == EnemyState::Idle => self.target = 1 - self.target,
+            EnemyState::Attack => {
+                self.sprite.reset();
+                self.hitbox_active = false;
+                self.attack_cooldown = ATTACK_COOLDOWN;
+            }
+            _ => {}
+        }
+
+        match state {
+            EnemyState::Chase | EnemyState::Return => self.path = None,
+            EnemyState::Attack => {
+                let to_target = self.chase_target.unwrap_or(self.center()) - self.center();
+                let facing = direction_from_vector(to_target, self.sprite.current().direction());
+                self.sprite.change(AnimationType::Attack1(facing));
+                self.sprite.reset();
+            }
+            EnemyState::Idle | EnemyState::Patrol => {}
+        }
+        self.state = state;
+        self.state_timer = 0.0;
+        self.blocked = false;
+    }
+
+    // Without a nav grid the path is empty, which heads straight for goal
+    fn plan(&mut self, goal: Vector2, world: &World) {
+        self.replan_timer = REPLAN_INTERVAL;
+        match &world.nav {
+            Some(nav) => {
+                self.path = nav.find_path(self.center(), goal);
+                self.path_goal = nav.tile_at(goal);
+            }
+            None => {
+                self.path = Some(Vec::new());
+                self.path_goal = None;
+            }
+        }
+    }
+
+    // Moves the center along the path towards goal, planning again when the
+    // goal moves to another tile. Returns how far it got, nothing when goal
+    // can't be reached.
+    fn follow_path(&mut self, goal: Vector2, world: &World, dt: f32) -> Vector2 {
+        self.replan_timer -= dt;
+        let goal_tile = world.nav.as_ref().and_then(|nav| nav.tile_at(goal));
+        if self.path.is_none() || self.replan_timer <= 0.0 || goal_tile != self.path_goal {
+            self.plan(goal, world);
+        }
+        let center = self.center();
+        let Some(path) = &mut self.path else {
+            return Vector2::zero();
+        };
+
+        // Waypoints already reached are dropped straight away so the walk
+        // doesn't pause at each one. Once inside the goal's tile the last
+        // stretch is straight at it.
+        while path.first().is_some_and(|waypoint| waypoint.distance_to(center) <= ARRIVE_THRESHOLD) {
+            path.remove(0);
+        }
+        let to_waypoint = path.first().copied().unwrap_or(goal) - center;
+        if to_waypoint.length() <= ARRIVE_THRESHOLD {
+            return Vector2::zero();
+        }
+
+        let step = to_waypoint.normalized() * (self.speed * dt).min(to_waypoint.length());
+        let moved = move_and_collide(self.bounds(), step, world.obstacles_for(self.filter));
+        self.pos += moved;
+        moved
+    }
+
+    // Patrol points are where pos goes, path goals are for the center
+    fn patrol_goal(&self) -> Vector2 {
+        self.patrol[self.target] + (self.center() - self.pos)
+    }
+
+    // A patrol point behind a wall is never reached, so once the walk there
+    // stops getting anywhere it turns back as if it had arrived. Sliding
+    // along the wall still counts as getting somewhere.
+    fn patrol(&mut self, dt: f32, world: &World) -> Vector2 {
+        let to_target = self.patrol[self.target] - self.pos;
+        let step = to_target.normalized() * (self.speed * dt).min(to_target.length());
+        let moved = move_and_collide(self.bounds(), step, world.obstacles_for(self.filter));
+        self.pos += moved;
+        self.blocked = step.length() > 0.0 && moved.length() < 0.001;
+        moved
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, view: Rectangle) {
+        self.sprite.draw(self.pos, view, d);
+    }
+}
+
+impl Drawable for Enemy {
+    fn depth(&self) -> f32 {
+        let bounds = self.sprite.bounds(self.pos);
+        bounds.y + bounds.height
+    }
+
+    fn render(&self, d: &mut RaylibDrawHandle, view: Rectangle, _alpha: f32) {
+        self.draw(d, view);
+    }
+}
+
+impl GameObject for Enemy {
+    fn update(&mut self, dt: f32, world: &World) {
+        let push = self.knockback.step(dt);
+        self.pos += move_and_collide(self.bounds(), push, world.obstacles_for(self.filter));
+        if !self.is_alive() {
+            self.sprite.animate(dt);
+            return;
+        }
+
+        self.state_timer += dt;
+        self.attack_cooldown = (self.attack_cooldown - dt).max(0.0);
+        self.unseen_timer = if self.target_visible { 0.0 } else { self.unseen_timer + dt };
+        let next = next_state(self.state, &self.senses());
+        if next != self.state {
+            self.enter(next);
+        }
+
+        let moved = match self.state {
+            EnemyState::Idle | EnemyState::Attack => Vector2::zero(),
+            EnemyState::Patrol => self.patrol(dt, world),
+            EnemyState::Chase => match self.chase_target {
+                Some(target) => self.follow_path(target, world, dt),
+                None => Vector2::zero(),
+            },
+            EnemyState::Return => {
+                let goal = self.patrol_goal();
+                self.follow_path(goal, world, dt)
+            }
+        };
+
+        if self.state != EnemyState::Attack {
+            let facing = direction_from_vector(moved, self.sprite.current().direction());
+            if moved.length() > 0.0 {
+                self.sprite.change(AnimationType::Run(facing));
+            } else {
+                self.sprite.change(AnimationType::Idle(facing));
+            }
+        }
+        let events = self.sprite.animate(dt);
+        if self.state == EnemyState::Attack && !events.is_empty() {
+            self.hitbox_active = true;
+        }
+    }
+
+    fn bounds(&self) -> Rectangle {
+        let scale = self.sprite.scale;
+        Rectangle::new(
+            self.pos.x + self.hitbox.x * scale,
+            self.pos.y + self.hitbox.y * scale,
+            self.hitbox.width * scale,
+            self.hitbox.height * scale,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Nobody around and nothing going on
+    fn calm() -> Senses {
+        Senses {
+            target_distance: None,
+            target_visible: false,
+            unseen_time: 0.0,
+            state_time: 0.0,
+            at_patrol_point: false,
+            home: false,
+            attack_ready: true,
+            attack_done: true,
+        }
+    }
+
+    #[test]
+    fn a_player_seen_close_by_is_chased() {
+        let seen = Senses { target_distance: Some(CHASE_RANGE - 1.0), target_visible: true, ..calm() };
+        assert_eq!(next_state(EnemyState::Patrol, &seen), EnemyState::Chase);
+        assert_eq!(next_state(EnemyState::Return, &seen), EnemyState::Chase);
+
+        let behind_a_wall = Senses { target_visible: false, ..seen };
+        assert_eq!(next_state(EnemyState::Patrol, &behind_a_wall), EnemyState::Patrol);
+    }
+
+    #[test]
+    fn chasing_gives_up_once_the_player_gets_away() {
+        let far = Senses { target_distance: Some(GIVE_UP_RANGE + 1.0), target_visible: true, ..calm() };
+        assert_eq!(next_state(EnemyState::Chase, &far), EnemyState::Return);
+
+        let hidden = Senses { target_distance: Some(CHASE_RANGE), unseen_time: LOSE_SIGHT_TIME + 0.1, ..calm() };
+        assert_eq!(next_state(EnemyState::Chase, &hidden), EnemyState::Return);
+
+        let home = Senses { home: true, ..calm() };
+        assert_eq!(next_state(EnemyState::Return, &home), EnemyState::Patrol);
+    }
+
+    #[test]
+    fn attacks_wait_for_the_cooldown_and_the_swing() {
+        let close = Senses { target_distance: Some(ATTACK_RANGE - 1.0), target_visible: true, ..calm() };
+        assert_eq!(next_state(EnemyState::Chase, &close), EnemyState::Attack);
+        assert_eq!(next_state(EnemyState::Chase, &Senses { attack_ready: false, ..close }), EnemyState::Chase);
+
+        let swinging = Senses { attack_done: false, ..close };
+        assert_eq!(next_state(EnemyState::Attack, &swinging), EnemyState::Attack);
+        assert_eq!(next_state(EnemyState::Attack, &close), EnemyState::Chase);
+    }
+
+    #[test]
+    fn patrols_wait_at_each_end() {
+        let arrived = Senses { at_patrol_point: true, ..calm() };
+        assert_eq!(next_state(EnemyState::Patrol, &arrived), EnemyState::Idle);
+        assert_eq!(next_state(EnemyState::Idle, &calm()), EnemyState::Idle);
+        assert_eq!(next_state(EnemyState::Idle, &Senses { state_time: IDLE_TIME, ..calm() }), EnemyState::Patrol);
+    }
+
+    // Walking from x 0 to 200 and back, in a world 400 across
+    fn patroller() -> Enemy {
+        let hitbox = Rectangle::new(0.0, 0.0, 10.0, 10.0);
+        Enemy::new(Vector2::new(0.0, 50.0), Vector2::new(200.0, 50.0), 60.0, AnimatedSprite::new(1.0), hitbox)
+    }
+
+    #[test]
+    fn a_patrol_stops_at_a_wall_and_turns_back_there() {
+        let mut world = World::new(Rectangle::new(0.0, 0.0, 400.0, 100.0));
+        world.add_obstacle(100.0, 0.0, 20.0, 100.0);
+        let mut enemy = patroller();
+
+        for _ in 0..120 {
+            enemy.update(1.0 / 60.0, &world);
+        }
+        assert_eq!(enemy.pos, Vector2::new(90.0, 50.0));
+        assert_eq!(enemy.state(), EnemyState::Idle);
+        assert_eq!(enemy.patrol, [Vector2::new(0.0, 50.0), Vector2::new(200.0, 50.0)]);
+
+        // Back the other way once it's waited
+        for _ in 0..90 {
+            enemy.update(1.0 / 60.0, &world);
+        }
+        assert_eq!(enemy.state(), EnemyState::Patrol);
+        assert!(enemy.pos.x < 90.0);
+    }
+
+    #[test]
+    fn a_knockback_mid_patrol_leaves_the_route_alone() {
+        let world = World::new(Rectangle::new(0.0, 0.0, 400.0, 100.0));
+        let mut enemy = patroller();
+        for _ in 0..60 {
+            enemy.update(1.0 / 60.0, &world);
+        }
+        enemy.apply_knockback(Vector2::new(-1.0, 0.0), 300.0);
+
+        let mut furthest: f32 = 0.0;
+        for _ in 0..300 {
+            enemy.update(1.0 / 60.0, &world);
+            furthest = furthest.max(enemy.pos.x);
+        }
+        assert_eq!(enemy.patrol, [Vector2::new(0.0, 50.0), Vector2::new(200.0, 50.0)]);
+        assert!(furthest >= 200.0 - ARRIVE_THRESHOLD);
+    }
+}
@@ -0,0 +1,82 @@
+use std::fmt::Write;
+
+use raylib::prelude::*;
+
+use crate::pool::Poolable;
+
+const RISE_SPEED: f32 = 40.0; // pixels per second
+const LIFETIME: f32 = 0.8;
+const FONT_SIZE: i32 = 10;
+
+// A bit of text, like a damage number, drifting up from where it was
+// spawned and fading out. They live in the world, so they scroll with it.
+pub struct FloatingText {
+    pub text: String,
+    pub pos: Vector2, // bottom center of the text
+    pub velocity: Vector2, // pixels per second
+    pub lifetime: f32, // seconds from spawning to disappearing
+    pub age: f32,
+    pub color: Color,
+    pub font_size: i32,
+}
+
+impl FloatingText {
+    pub fn new(text: &str, pos: Vector2, color: Color) -> FloatingText {
+        FloatingText {
+            text: text.to_string(),
+            pos,
+            velocity: Vector2::new(0.0, -RISE_SPEED),
+            lifetime: LIFETIME,
+            age: 0.0,
+            color,
+            font_size: FONT_SIZE,
+        }
+    }
+
+    // Written into the existing string so a reused one doesn't allocate
+    pub fn show(&mut self, text: &str, pos: Vector2, color: Color) {
+        self.text.clear();
+        self.text.push_str(text);
+        self.pos = pos;
+        self.color = color;
+    }
+
+    // What was lost, just above the top of whatever lost it. Written into
+    // the existing string too.
+    pub fn show_damage(&mut self, amount: i32, bounds: Rectangle, color: Color) {
+        self.text.clear();
+        let _ = write!(self.text, "{}", amount);
+        self.pos = Vector2::new(bounds.x + bounds.width / 2.0, bounds.y);
+        self.color = color;
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.age += dt;
+        self.pos += self.velocity * dt;
+    }
+
+    // World space
+    pub fn draw(&self, d: &mut RaylibDrawHandle, view: Rectangle) {
+        let width = d.measure_text(&self.text, self.font_size) as f32;
+        let rect = Rectangle::new(self.pos.x - width / 2.0, self.pos.y - self.font_size as f32, width, self.font_size as f32);
+        if !rect.check_collision_recs(&view) {
+            return;
+        }
+        let fade = 1.0 - self.age / self.lifetime;
+        d.draw_text(&self.text, rect.x as i32, rect.y as i32, self.font_size, self.color.alpha(fade));
+    }
+}
+
+impl Poolable for FloatingText {
+    fn reset(&mut self) {
+        self.text.clear();
+        self.velocity = Vector2::new(0.0, -RISE_SPEED);
+        self.lifetime = LIFETIME;
+        self.age = 0.0;
+        self.font_size = FONT_SIZE;
+    }
+
+    fn is_expired(&self) -> bool {
+        self.age >= self.lifetime
+    }
+}
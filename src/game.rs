@@ -0,0 +1,1507 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use raylib::prelude::*;
+
+use crate::animation::{AnimatedSprite, Direction, load_animations};
+use crate::audio::AudioManager;
+use crate::camera::{FollowCamera, camera_view};
+use crate::config::Config;
+use crate::daynight::DayNightCycle;
+use crate::dialogue::DialogueBox;
+use crate::drawable::{Drawable, draw_by_depth};
+use crate::enemy::Enemy;
+use crate::floating_text::FloatingText;
+use crate::minimap::Minimap;
+use crate::npc::Npc;
+use crate::object::GameObject;
+use crate::objective::{Objective, ObjectiveKind, Progress};
+use crate::parallax::ParallaxBackground;
+use crate::particles::ParticleSystem;
+use crate::physics::can_collide;
+use crate::input::{InputBindings, InputState};
+use crate::inventory::ItemKind;
+use crate::item::Item;
+use crate::lighting::Lighting;
+use crate::player::{Player, PlayerBuilder};
+use crate::pool::Pool;
+use crate::projectile::{MAX_PROJECTILES, Projectile};
+use crate::rng::Rng;
+use crate::save::{CheckpointSnapshot, EnemySnapshot, ItemSnapshot, LevelSnapshot, WorldSnapshot};
+use crate::screen::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+use crate::spatial_hash::SpatialHash;
+use crate::spawn::pick_enemy_spawns;
+use crate::textures::TextureManager;
+use crate::tiled::load_tiled;
+use crate::tilemap::TileMap;
+use crate::transition::Transition;
+use crate::trigger::{Trigger, TriggerAction};
+use crate::weather::{Weather, WeatherKind, WeatherSettings};
+use crate::world::World;
+
+const BACKGROUND_COLOR: u32 = 0x181818FF;
+// There's no Death in it, so the hero and enemies fall back to Idle when
+// they die and nothing waits for them to fall over
+const HERO_ANIMATIONS: &str = "resources/Hero/animations.json";
+// Where the body is in the hero sheet's frames, enemies are drawn from it too
+const HERO_COLLISION: Rectangle = Rectangle { x: 41.0, y: 40.0, width: 12.0, height: 17.0 };
+const SPRITE_SCALE: f32 = 1.5; // for every character and item sheet
+const COIN_ANIMATIONS: &str = "resources/Items/animations.json";
+const COIN_ICON: &str = "resources/Items/coin.png"; // first frame is the icon
+// Built in, so firing never depends on finding the file
+const PROJECTILE_SHEET: &[u8] = include_bytes!("../resources/Projectiles/orb.png");
+const SAVE_FILE: &str = "save.json";
+const KNOCKBACK_STRENGTH: f32 = 400.0; // pixels per second at the start of a push
+pub const FIXED_DT: f32 = 1.0 / 60.0; // every update advances the game by exactly this much
+
+// Where each HUD element sits on screen, stacked down from the top left so
+// none of them overlap
+struct HudLayout {
+    health_bar: Rectangle,
+    level: Vector2, // top left of the text
+    coins: Vector2, // top left of the icon, the count follows it
+    objectives: Vector2, // top left of the list, under the first player's HUD
+    objective_spacing: f32,
+    icon_size: f32,
+    inventory_columns: usize, // the panel is centered, slots fill rows left to right
+    slot_size: f32,
+    slot_gap: f32,
+}
+
+const HUD: HudLayout = HudLayout {
+    health_bar: Rectangle { x: 10.0, y: 10.0, width: 120.0, height: 12.0 },
+    level: Vector2 { x: 138.0, y: 10.0 },
+    coins: Vector2 { x: 10.0, y: 30.0 },
+    objectives: Vector2 { x: 10.0, y: 58.0 },
+    objective_spacing: 14.0,
+    icon_size: 20.0,
+    inventory_columns: 4,
+    slot_size: 44.0,
+    slot_gap: 6.0,
+};
+
+// Everything that differs between levels. Everything placed on the map
+// itself comes from its objects: a "spawn" named "player" (and optionally
+// "player2"...), others to arrive at through doors, a "coin" point for each
+// coin and "trigger" areas (see Trigger::from_object).
+struct Level {
+    map: &'static str,
+    music: &'static str,
+    background: &'static [(&'static str, f32)], // texture and scroll factor, furthest first
+    npcs: &'static [(Vector2, &'static str)], // where each stands and what they say
+    patrols: &'static [(Vector2, Vector2)], // one enemy walking between each pair
+    max_enemies: usize, // the map's spawn points fill in whatever the patrols leave of this
+    weather: Option<WeatherSettings>, // clear skies without
+}
+
+const FIRST_LEVEL: Level = Level {
+    map: "resources/level1.tmj",
+    music: "resources/Audio/theme.wav",
+    background: &[
+        ("resources/Backgrounds/sky.png", 0.0),
+        ("resources/Backgrounds/stars.png", 0.2),
+    ],
+    npcs: &[
+        (Vector2::new(420.0, 80.0), "Hello there! The slimes have been restless lately.\n\nIf you find any coins lying around, hold on to them."),
+        (Vector2::new(200.0, 420.0), "I'd stay away from the pond if I were you. Nobody knows how deep it goes."),
+    ],
+    patrols: &[
+        (Vector2::new(40.0, 300.0), Vector2::new(460.0, 300.0)),
+        (Vector2::new(680.0, 40.0), Vector2::new(680.0, 440.0)),
+    ],
+    max_enemies: 4,
+    weather: Some(WeatherSettings {
+        kind: WeatherKind::Rain,
+        density: 150,
+        wind: -60.0,
+        sound: Some("resources/Audio/rain.wav"),
+    }),
+};
+
+const CAVE_LEVEL: Level = Level {
+    map: "resources/level2.tmj",
+    music: "resources/Audio/theme.wav",
+    background: &[],
+    npcs: &[
+        (Vector2::new(440.0, 200.0), "Careful, it's easy to get turned around in here. The way out is back west."),
+    ],
+    patrols: &[
+        (Vector2::new(400.0, 64.0), Vector2::new(400.0, 360.0)),
+    ],
+    max_enemies: 2,
+    weather: None, // it's underground
+};
+
+const LEVELS: [&Level; 2] = [&FIRST_LEVEL, &CAVE_LEVEL]; // doors find theirs by map
+
+const TEXT_MAP_TILE_SIZE: f32 = 32.0;
+const PLAYER_SPACING: f32 = 40.0; // between players sharing a spawn
+const INTERACT_RADIUS: f32 = 48.0; // from the player's center to an NPC's
+const LIGHT_RADIUS: f32 = 160.0; // screen pixels around the player
+const ENEMY_XP: u32 = 5; // for whoever lands the killing blow
+const RESPAWN_COST: u32 = 3; // coins each player drops coming back after everyone's died
+const ENEMY_SPACING: f32 = 40.0; // between enemies a trigger spawns together
+const ENEMY_PATROL: f32 = 80.0; // furthest a spawned enemy walks from where it appeared
+const ENEMY_GRID_CELL: f32 = 64.0; // pixels, a few enemies wide
+const REPLAY_SEED: u32 = 0x2545F491; // recordings without a seed in the config all share this one
+const HOT_RELOAD_INTERVAL: f32 = 1.0; // seconds between looking for changed sprite sheets
+const FADE_TIME: f32 = 0.8; // seconds to fade out and back in through a door
+const PLAYER_DAMAGE_COLOR: Color = Color { r: 255, g: 80, b: 80, a: 255 };
+const MAX_FLOATING_TEXTS: usize = 32;
+const ENEMY_DAMAGE_COLOR: Color = Color { r: 255, g: 230, b: 120, a: 255 };
+const OBJECTIVES: [ObjectiveKind; 3] = [
+    ObjectiveKind::CollectCoins(5),
+    ObjectiveKind::DefeatEnemies(3),
+    ObjectiveKind::ReachCheckpoint,
+];
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+enum MenuOption {
+    Start,
+    Quit,
+}
+
+const MENU_OPTIONS: [MenuOption; 2] = [MenuOption::Start, MenuOption::Quit];
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum GameState {
+    MainMenu,
+    Playing,
+    Paused,
+    Transition, // fading through a door or back to a checkpoint, nobody moves
+    LevelComplete, // every objective is done, until the players carry on exploring
+}
+
+// Where the fade is headed once the screen is black
+enum Destination {
+    Door(&'static Level, String), // and the spawn to arrive at
+    Respawn,
+}
+
+// Where everyone was and how healthy, the last time someone reached a
+// checkpoint. Players come back like that after they've all died.
+#[derive(Clone)]
+struct Checkpoint {
+    level: &'static Level,
+    positions: Vec<Vector2>, // centers, one per player
+    health: Vec<i32>,
+}
+
+// What was left of a level the players walked out of: whoever's still
+// standing, whatever's still lying around and which triggers have gone off.
+// Put back when they come back, so coins can't be picked up twice and the
+// dead stay dead.
+struct LeftBehind {
+    enemies: Vec<Enemy>,
+    items: Vec<Item>, // only the ones not picked up
+    fired: Vec<bool>, // each trigger's, in the map's order
+}
+
+impl LeftBehind {
+    fn take(enemies: &mut Vec<Enemy>, items: &mut Vec<Item>, triggers: &[Trigger]) -> LeftBehind {
+        let mut items = std::mem::take(items);
+        items.retain(|item| !item.collected);
+        LeftBehind {
+            enemies: std::mem::take(enemies),
+            items,
+            fired: triggers.iter().map(|trigger| trigger.fired).collect(),
+        }
+    }
+
+    // In place of what loading the level afresh spawned. A map that's
+    // changed since gets its triggers fresh.
+    fn put_back(self, enemies: &mut Vec<Enemy>, items: &mut Vec<Item>, triggers: &mut [Trigger]) {
+        *enemies = self.enemies;
+        *items = self.items;
+        if triggers.len() == self.fired.len() {
+            for (trigger, fired) in triggers.iter_mut().zip(self.fired) {
+                trigger.fired = fired;
+            }
+        }
+    }
+}
+
+pub struct Game<'aud> {
+    state: GameState,
+    level: &'static Level,
+    transition: Transition,
+    destination: Option<Destination>, // loaded halfway through the fade
+    checkpoint: Option<Checkpoint>, // none reached yet respawns at the level's start
+    left_behind: HashMap<&'static str, LeftBehind>, // by map, every level left since the game started
+    objectives: Vec<Objective>,
+    kills: u32, // enemies defeated by anyone, for the objectives
+    players: Vec<Player>, // the first is the one saved and shown in the inventory
+    npcs: Vec<Npc>,
+    enemies: Vec<Enemy>,
+    enemy_grid: SpatialHash, // the enemies' bounds as of this update, for hit checks
+    items: Vec<Item>,
+    triggers: Vec<Trigger>,
+    projectiles: Pool<Projectile>,
+    player_spawns: Vec<Vector2>, // one per player
+    coin_icon: Rc<Texture2D>,
+    background: ParallaxBackground,
+    weather: Option<Weather>, // the level's, if it has any
+    weather_kind: Option<WeatherKind>, // from the config, in place of the level's own
+    tilemap: TileMap,
+    minimap: Minimap,
+    world: World,
+    cameras: Vec<FollowCamera>, // one framing everyone, or one per player in split screen
+    split_views: Vec<RenderTexture2D>, // one per player in split screen, otherwise none
+    bindings: Vec<InputBindings>, // one per player
+    textures: TextureManager,
+    hot_reload_timer: Option<f32>, // seconds to the next look for changed files, None unless hot_reload is on
+    audio: AudioManager<'aud>,
+    particles: ParticleSystem,
+    rng: Rng, // everything random in play goes through this one
+    floating_texts: Pool<FloatingText>,
+    day_night: DayNightCycle,
+    lighting: Lighting,
+    lighting_enabled: bool, // off for weak hardware
+    menu_selection: usize, // index into MENU_OPTIONS
+    quit: bool,
+    debug: bool, // F3 overlay
+    inventory_open: bool,
+    dialogue: DialogueBox,
+    pub debug_outline_color: Color,
+}
+
+// Moves like PlayerBuilder's default unless the config tunes it
+fn spawn_player(rl: &mut RaylibHandle, thread: &RaylibThread, textures: &mut TextureManager, config: &Config, spawn: Vector2) -> Result<Player, String> {
+    let mut builder = PlayerBuilder::new().collision(HERO_COLLISION).scale(SPRITE_SCALE);
+    if let Some(speed) = config.player_speed {
+        builder = builder.speed(speed);
+    }
+    if let Some(acceleration) = config.player_acceleration {
+        builder = builder.acceleration(acceleration);
+    }
+    if let Some(friction) = config.player_friction {
+        builder = builder.friction(friction);
+    }
+    let mut player = builder.build();
+    load_animations(rl, thread, textures, &mut player.sprite, HERO_ANIMATIONS)?;
+    player.teleport(spawn);
+    Ok(player)
+}
+
+fn spawn_enemy(rl: &mut RaylibHandle, thread: &RaylibThread, textures: &mut TextureManager, start: Vector2, end: Vector2) -> Result<Enemy, String> {
+    let mut sprite = AnimatedSprite::new(SPRITE_SCALE);
+    load_animations(rl, thread, textures, &mut sprite, HERO_ANIMATIONS)?;
+    Ok(Enemy::new(start, end, 90.0, sprite, HERO_COLLISION))
+}
+
+fn spawn_coin(rl: &mut RaylibHandle, thread: &RaylibThread, textures: &mut TextureManager, center: Vector2) -> Result<Item, String> {
+    let mut sprite = AnimatedSprite::new(SPRITE_SCALE);
+    load_animations(rl, thread, textures, &mut sprite, COIN_ANIMATIONS)?;
+    let size = sprite.bounds(Vector2::zero());
+    Ok(Item::new(ItemKind::Coin, center - Vector2::new(size.width / 2.0, size.height / 2.0), sprite))
+}
+
+// An enemy centered on center, wandering a little way either side of it
+// The living ones
+fn snapshot_enemies(enemies: &[Enemy]) -> Vec<EnemySnapshot> {
+    enemies.iter()
+        .filter(|enemy| enemy.is_alive())
+        .map(|enemy| EnemySnapshot { pos: enemy.pos, patrol: enemy.patrol, speed: enemy.speed, health: enemy.health })
+        .collect()
+}
+
+// The ones not picked up yet
+fn snapshot_items(items: &[Item]) -> Vec<ItemSnapshot> {
+    items.iter()
+        .filter(|item| !item.collected)
+        .map(|item| {
+            let bounds = item.bounds();
+            ItemSnapshot { kind: item.kind, center: item.pos + Vector2::new(bounds.width / 2.0, bounds.height / 2.0) }
+        })
+        .collect()
+}
+
+fn restore_enemies(rl: &mut RaylibHandle, thread: &RaylibThread, textures: &mut TextureManager, saved: &[EnemySnapshot]) -> Result<Vec<Enemy>, String> {
+    saved.iter()
+        .map(|saved| {
+            let mut enemy = spawn_enemy(rl, thread, textures, saved.patrol[0], saved.patrol[1])?;
+            enemy.pos = saved.pos;
+            enemy.speed = saved.speed;
+            enemy.health = saved.health;
+            Ok(enemy)
+        })
+        .collect()
+}
+
+fn restore_items(rl: &mut RaylibHandle, thread: &RaylibThread, textures: &mut TextureManager, saved: &[ItemSnapshot]) -> Result<Vec<Item>, String> {
+    saved.iter()
+        .map(|saved| match saved.kind {
+            ItemKind::Coin => spawn_coin(rl, thread, textures, saved.center),
+        })
+        .collect()
+}
+
+fn spawn_enemy_around(
+    rl: &mut RaylibHandle, thread: &RaylibThread,
+    textures: &mut TextureManager,
+    center: Vector2,
+    rng: &mut Rng) -> Result<Enemy, String>
+{
+    let mut enemy = spawn_enemy(rl, thread, textures, Vector2::zero(), Vector2::zero())?;
+    let start = center - enemy.center();
+    let end = start + Vector2::new(rng.range_f32(-ENEMY_PATROL, ENEMY_PATROL), 0.0);
+    enemy.pos = start;
+    enemy.patrol = [start, end];
+    Ok(enemy)
+}
+
+// A random few of the map's enemy spawn points filled in, each enemy
+// centered on its point and wandering a little way from it
+fn spawn_enemies(
+    rl: &mut RaylibHandle, thread: &RaylibThread,
+    textures: &mut TextureManager,
+    tilemap: &TileMap,
+    rng: &mut Rng,
+    cap: usize) -> Result<Vec<Enemy>, String>
+{
+    let mut enemies = Vec::new();
+    for spawn in pick_enemy_spawns(tilemap, rng, cap)? {
+        let mut enemy = spawn_enemy_around(rl, thread, textures, spawn.pos, rng)?;
+        enemy.speed = spawn.kind.speed();
+        enemy.health = spawn.kind.health();
+        enemies.push(enemy);
+    }
+    Ok(enemies)
+}
+
+// Leveling up is celebrated where the player stands
+fn award_kill(player: &mut Player, particles: &mut ParticleSystem, rng: &mut Rng, audio: &AudioManager) {
+    if player.add_xp(ENEMY_XP) > 0 {
+        audio.play("levelup");
+        particles.spawn_burst(player.center(), 32, rng);
+    }
+}
+
+// The first player's spawn is required, the others ("name2"...) fall back
+// to standing in a row beside it
+fn find_player_spawns(tilemap: &TileMap, count: usize, name: &str, map: &str) -> Result<Vec<Vector2>, String> {
+    let spawn = |name: &str| tilemap.objects_of_kind("spawn")
+        .find(|object| object.name == name)
+        .map(|object| Vector2::new(object.rect.x, object.rect.y));
+    let first = spawn(name).ok_or(format!("Map {} has no spawn named {}", map, name))?;
+    Ok((0..count)
+        .map(|i| match i {
+            0 => first,
+            _ => spawn(&format!("{}{}", name, i + 1)).unwrap_or(first + Vector2::new(PLAYER_SPACING * i as f32, 0.0)),
+        })
+        .collect())
+}
+
+// Maps saved from Tiled are JSON, anything else is the plain text format
+fn load_map(rl: &mut RaylibHandle, thread: &RaylibThread, textures: &mut TextureManager, path: &str) -> Result<TileMap, String> {
+    if path.ends_with(".tmj") || path.ends_with(".json") {
+        load_tiled(rl, thread, textures, path)
+    } else {
+        TileMap::load(path, TEXT_MAP_TILE_SIZE)
+    }
+}
+
+// World space, just above whoever can be talked to
+fn draw_interact_prompt(d: &mut RaylibDrawHandle, bounds: Rectangle) {
+    let text = "Press E";
+    let font_size = 10;
+    let width = d.measure_text(text, font_size) as f32;
+    let x = bounds.x + (bounds.width - width) / 2.0;
+    d.draw_text(text, x as i32, bounds.y as i32 - font_size, font_size, Color::WHITE);
+}
+
+fn draw_centered_text(d: &mut RaylibDrawHandle, text: &str, y: i32, font_size: i32, color: Color) {
+    let text_width = d.measure_text(text, font_size);
+    d.draw_text(text, (VIRTUAL_WIDTH - text_width) / 2, y, font_size, color);
+}
+
+fn draw_dim_overlay(d: &mut RaylibDrawHandle) {
+    d.draw_rectangle(0, 0, VIRTUAL_WIDTH, VIRTUAL_HEIGHT, Color::BLACK.alpha(0.5));
+}
+
+impl<'aud> Game<'aud> {
+    // A fresh game ignores the save file and falls back on a fixed seed, so
+    // recordings always start alike
+    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread, audio: &'aud RaylibAudio, config: &Config, fresh: bool) -> Result<Game<'aud>, String> {
+        let w = VIRTUAL_WIDTH as f32;
+        let h = VIRTUAL_HEIGHT as f32;
+
+        // The players are put in place once the level is loaded
+        let mut textures = TextureManager::new();
+        let mut players = Vec::new();
+        for _ in 0..config.players.max(1) {
+            players.push(spawn_player(rl, thread, &mut textures, config, Vector2::zero())?);
+        }
+
+        let coin_icon = textures.load(rl, thread, COIN_ICON)?;
+        let projectile_sheet = textures.load_from_memory(rl, thread, "orb", ".png", PROJECTILE_SHEET)?;
+
+        // Split screen gives each player a column of the screen
+        let mut cameras = Vec::new();
+        let mut split_views = Vec::new();
+        if config.split_screen && players.len() > 1 {
+            let width = w / players.len() as f32;
+            for player in &players {
+                cameras.push(FollowCamera::new(player.center(), width, h, 1.0));
+                let view = rl.load_render_texture(thread, width as u32, h as u32)
+                    .map_err(|e| format!("Couldn't create a split screen view: {}", e))?;
+                split_views.push(view);
+            }
+        } else {
+            cameras.push(FollowCamera::new(players[0].center(), w, h, 1.0));
+        }
+
+        let mut audio = AudioManager::new(audio);
+        audio.load("footstep", "resources/Audio/footstep.wav")?;
+        audio.load("blip", "resources/Audio/blip.wav")?;
+        audio.load("coin", "resources/Audio/coin.wav")?;
+        audio.load("levelup", "resources/Audio/levelup.wav")?;
+        audio.set_volume(0.6);
+        audio.play_music(FIRST_LEVEL.music)?;
+
+        let tilemap = TileMap::parse("", TEXT_MAP_TILE_SIZE)?;
+        let mut game = Game {
+            state: GameState::MainMenu,
+            level: &FIRST_LEVEL,
+            transition: Transition::new(FADE_TIME),
+            destination: None,
+            checkpoint: None,
+            left_behind: HashMap::new(),
+            objectives: OBJECTIVES.into_iter().map(Objective::new).collect(),
+            kills: 0,
+            bindings: (0..players.len())
+                .map(|i| InputBindings { mouse: i == 0 && config.mouse_aim, ..InputBindings::for_player(i) })
+                .collect(),
+            players,
+            npcs: Vec::new(),
+            enemies: Vec::new(),
+            enemy_grid: SpatialHash::new(ENEMY_GRID_CELL),
+            items: Vec::new(),
+            triggers: Vec::new(),
+            projectiles: Pool::new(MAX_PROJECTILES, move || Projectile::new(projectile_sheet.clone())),
+            player_spawns: Vec::new(),
+            coin_icon,
+            background: ParallaxBackground::new(),
+            weather: None,
+            weather_kind: config.weather,
+            minimap: Minimap::new(&tilemap, config.minimap_size as f32, config.minimap_corner, config.minimap),
+            tilemap,
+            world: World::new(Rectangle::default()),
+            cameras,
+            split_views,
+            textures,
+            hot_reload_timer: config.hot_reload.then_some(HOT_RELOAD_INTERVAL),
+            audio,
+            particles: ParticleSystem::new(),
+            rng: match config.seed {
+                Some(seed) => Rng::new(seed),
+                None if fresh => Rng::new(REPLAY_SEED),
+                None => Rng::from_clock(),
+            },
+            floating_texts: Pool::new(MAX_FLOATING_TEXTS, || FloatingText::new("", Vector2::zero(), Color::WHITE)),
+            day_night: DayNightCycle::new(0.1),
+            lighting: Lighting::new(rl, thread, LIGHT_RADIUS)?,
+            lighting_enabled: config.lighting,
+            menu_selection: 0,
+            quit: false,
+            debug: false,
+            inventory_open: false,
+            dialogue: DialogueBox::new(),
+            debug_outline_color: Color::MAGENTA,
+        };
+        game.load_level(rl, thread, &FIRST_LEVEL, "player")?;
+
+        if !fresh && std::path::Path::new(SAVE_FILE).exists() {
+            let restored = WorldSnapshot::load(SAVE_FILE)
+                .and_then(|snapshot| game.restore(rl, thread, &snapshot));
+            if let Err(e) = restored {
+                eprintln!("WARNING: {}, starting from the beginning", e);
+            }
+        }
+        // A save edited by hand could put the players anywhere
+        for player in &mut game.players {
+            player.clamp_to_bounds(game.world.bounds);
+        }
+        game.snap_cameras();
+        Ok(game)
+    }
+
+    // A level without weather stays clear whatever the config asks for.
+    // Anything else falling goes without the level's sound, that was for its own.
+    fn weather_for(&self, level: &Level) -> Option<WeatherSettings> {
+        let mut weather = level.weather?;
+        if let Some(kind) = self.weather_kind && kind != weather.kind {
+            weather.kind = kind;
+            weather.sound = None;
+        }
+        Some(weather)
+    }
+
+    // Swaps everything on the map for level's, with the players standing at
+    // the spawn called spawn. What they carry comes along. A level they've
+    // been in before is as they left it, loading the one they're in starts it
+    // over.
+    fn load_level(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, level: &'static Level, spawn: &str) -> Result<(), String> {
+        let tilemap = load_map(rl, thread, &mut self.textures, level.map)?;
+        let mut world = World::new(tilemap.bounds());
+        for rect in tilemap.solid_rects() {
+            world.add_obstacle(rect.x, rect.y, rect.width, rect.height);
+        }
+        world.build_nav_grid(tilemap.tile_size, true);
+        let player_spawns = find_player_spawns(&tilemap, self.players.len(), spawn, level.map)?;
+
+        let mut npcs = Vec::new();
+        for &(pos, dialogue) in level.npcs {
+            let mut sprite = AnimatedSprite::new(SPRITE_SCALE);
+            load_animations(rl, thread, &mut self.textures, &mut sprite, HERO_ANIMATIONS)?;
+            npcs.push(Npc::new(pos, sprite, dialogue));
+        }
+
+        let mut triggers = tilemap.objects_of_kind("trigger")
+            .map(|object| Trigger::from_object(object)
+                .map_err(|e| format!("Couldn't load a trigger in map {}: {}", level.map, e)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut enemies = Vec::new();
+        let mut items = Vec::new();
+        if !self.left_behind.contains_key(level.map) {
+            for &(start, end) in level.patrols {
+                enemies.push(spawn_enemy(rl, thread, &mut self.textures, start, end)?);
+            }
+            let cap = level.max_enemies.saturating_sub(enemies.len());
+            enemies.extend(spawn_enemies(rl, thread, &mut self.textures, &tilemap, &mut self.rng, cap)
+                .map_err(|e| format!("Couldn't spawn enemies in map {}: {}", level.map, e))?);
+
+            // Coin points mark where the middle of the coin goes
+            for object in tilemap.objects_of_kind("coin") {
+                items.push(spawn_coin(rl, thread, &mut self.textures, Vector2::new(object.rect.x, object.rect.y))?);
+            }
+        }
+
+        let mut background = ParallaxBackground::new();
+        for &(path, scroll_factor) in level.background {
+            background.add_layer(self.textures.load(rl, thread, path)?, scroll_factor);
+        }
+
+        // Only once everything has loaded, so a broken level leaves the old one be
+        if level.music != self.level.music {
+            self.audio.play_music(level.music)?;
+        }
+        // Nothing is playing yet when the first level loads
+        let weather = self.weather_for(level);
+        let sound = weather.and_then(|weather| weather.sound);
+        if sound != self.weather_for(self.level).and_then(|weather| weather.sound) || self.weather.is_none() {
+            self.audio.play_ambience(sound)?;
+        }
+        for (player, &spawn) in self.players.iter_mut().zip(&player_spawns) {
+            player.teleport(spawn);
+        }
+        if level.map != self.level.map {
+            let left = LeftBehind::take(&mut self.enemies, &mut self.items, &self.triggers);
+            self.left_behind.insert(self.level.map, left);
+        }
+        if let Some(left) = self.left_behind.remove(level.map) {
+            left.put_back(&mut enemies, &mut items, &mut triggers);
+        }
+        self.level = level;
+        self.minimap.set_map(&tilemap);
+        self.tilemap = tilemap;
+        world.clock = self.world.clock;
+        self.world = world;
+        self.player_spawns = player_spawns;
+        self.npcs = npcs;
+        self.enemies = enemies;
+        self.triggers = triggers;
+        self.items = items;
+        self.background = background;
+        self.weather = weather.as_ref().map(Weather::new);
+        self.projectiles.clear();
+        self.snap_cameras();
+        Ok(())
+    }
+
+    // A point on the virtual screen to the world under it, through whichever
+    // view shows that player
+    fn screen_to_world(&self, rl: &RaylibHandle, player: usize, pos: Vector2) -> Vector2 {
+        if self.split_views.is_empty() {
+            return rl.get_screen_to_world2D(pos, self.cameras[0].camera);
+        }
+        let left: i32 = self.split_views[..player].iter().map(|view| view.texture.width).sum();
+        rl.get_screen_to_world2D(pos - Vector2::new(left as f32, 0.0), self.cameras[player].camera)
+    }
+
+    fn snap_cameras(&mut self) {
+        if self.split_views.is_empty() {
+            let area = self.player_area(1.0);
+            self.cameras[0].snap_to(Vector2::new(area.x + area.width / 2.0, area.y + area.height / 2.0));
+        } else {
+            for (camera, player) in self.cameras.iter_mut().zip(&self.players) {
+                camera.snap_to(player.center());
+            }
+        }
+        self.reveal_minimap();
+    }
+
+    // Marks what every camera can see as explored
+    fn reveal_minimap(&mut self) {
+        for camera in &self.cameras {
+            let size = camera.camera.offset * 2.0;
+            self.minimap.reveal(camera_view(&camera.camera, size.x, size.y));
+        }
+    }
+
+    pub fn snapshot(&self) -> WorldSnapshot {
+        WorldSnapshot {
+            map: self.level.map.to_string(),
+            players: self.players.iter().map(Player::snapshot).collect(),
+            enemies: snapshot_enemies(&self.enemies),
+            items: snapshot_items(&self.items),
+            triggers: self.triggers.iter().map(|trigger| trigger.fired).collect(),
+            levels: self.left_behind.iter()
+                .map(|(map, left)| LevelSnapshot {
+                    map: map.to_string(),
+                    enemies: snapshot_enemies(&left.enemies),
+                    items: snapshot_items(&left.items),
+                    triggers: left.fired.clone(),
+                })
+                .collect(),
+            checkpoint: self.checkpoint.as_ref().map(|checkpoint| CheckpointSnapshot {
+                map: checkpoint.level.map.to_string(),
+                positions: checkpoint.positions.clone(),
+                health: checkpoint.health.clone(),
+            }),
+            objectives: self.objectives.clone(),
+            kills: self.kills,
+            clock: self.world.clock,
+            rng: self.rng.state(),
+        }
+    }
+
+    // Loads the snapshot's level and puts everything back as it was. Players
+    // the snapshot doesn't have stay at the level's spawns.
+    pub fn restore(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, snapshot: &WorldSnapshot) -> Result<(), String> {
+        let level = LEVELS.iter().find(|level| level.map == snapshot.map)
+            .ok_or(format!("No level has the map {}", snapshot.map))?;
+        let checkpoint = match &snapshot.checkpoint {
+            Some(saved) => Some(Checkpoint {
+                level: LEVELS.into_iter().find(|level| level.map == saved.map)
+                    .ok_or(format!("No level has the checkpoint's map {}", saved.map))?,
+                positions: saved.positions.clone(),
+                health: saved.health.clone(),
+            }),
+            None => None,
+        };
+        self.load_level(rl, thread, level, "player")?;
+
+        let enemies = restore_enemies(rl, thread, &mut self.textures, &snapshot.enemies)?;
+        let items = restore_items(rl, thread, &mut self.textures, &snapshot.items)?;
+        let mut left_behind = HashMap::new();
+        for saved in &snapshot.levels {
+            let map = LEVELS.iter().find(|level| level.map == saved.map)
+                .ok_or(format!("No level has the map {}", saved.map))?
+                .map;
+            left_behind.insert(map, LeftBehind {
+                enemies: restore_enemies(rl, thread, &mut self.textures, &saved.enemies)?,
+                items: restore_items(rl, thread, &mut self.textures, &saved.items)?,
+                fired: saved.triggers.clone(),
+            });
+        }
+
+        for (player, saved) in self.players.iter_mut().zip(&snapshot.players) {
+            player.restore(saved);
+        }
+        self.enemies = enemies;
+        self.items = items;
+        self.left_behind = left_behind;
+        // A map that's changed since gets its triggers fresh
+        if self.triggers.len() == snapshot.triggers.len() {
+            for (trigger, &fired) in self.triggers.iter_mut().zip(&snapshot.triggers) {
+                trigger.fired = fired;
+            }
+        }
+        self.checkpoint = checkpoint;
+        // The list is the game's, a snapshot only says which are done
+        for objective in &mut self.objectives {
+            objective.complete = snapshot.objectives.iter()
+                .any(|saved| saved.kind == objective.kind && saved.complete);
+        }
+        self.kills = snapshot.kills;
+        self.world.clock = snapshot.clock;
+        self.rng = Rng::from_state(snapshot.rng);
+        Ok(())
+    }
+
+    // Everything drawn from a sheet that's changed on disk switches over to
+    // the new one, without restarting its animation
+    fn reload_textures(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) {
+        for (old, new) in self.textures.reload_changed(rl, thread) {
+            let sprites = self.players.iter_mut().map(|player| &mut player.sprite)
+                .chain(self.npcs.iter_mut().map(|npc| &mut npc.sprite))
+                .chain(self.enemies.iter_mut().map(|enemy| &mut enemy.sprite))
+                .chain(self.items.iter_mut().map(|item| &mut item.sprite))
+                .chain(self.left_behind.values_mut().flat_map(|left| {
+                    left.enemies.iter_mut().map(|enemy| &mut enemy.sprite)
+                        .chain(left.items.iter_mut().map(|item| &mut item.sprite))
+                }));
+            for sprite in sprites {
+                sprite.replace_texture(&old, &new);
+            }
+        }
+    }
+
+    // Keeps the run going next launch, a dead player starts over instead
+    pub fn save(&self) -> Result<(), String> {
+        if self.players[0].is_alive() {
+            self.snapshot().save(SAVE_FILE)
+        } else {
+            match std::fs::remove_file(SAVE_FILE) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    Err(format!("Couldn't remove save {}: {}", SAVE_FILE, e))
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    // One state per player, in order
+    pub fn poll_input(&self, rl: &RaylibHandle) -> Vec<InputState> {
+        self.bindings.iter().map(|bindings| InputState::poll(rl, bindings)).collect()
+    }
+
+    // Menus, pausing and the like only listen to the first player. Players
+    // missing from inputs, as in a replay recorded with fewer, stand still.
+    pub fn update(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, inputs: &[InputState], dt: f32) -> Result<(), String> {
+        let idle = InputState::default();
+        let input = inputs.first().unwrap_or(&idle);
+        self.audio.update_music();
+        if input.mute_pressed {
+            self.audio.toggle_mute();
+        }
+        if input.debug_pressed {
+            self.debug = !self.debug;
+        }
+        // Even while paused, so art can be checked on a still frame
+        if let Some(timer) = &mut self.hot_reload_timer {
+            *timer -= dt;
+            if *timer <= 0.0 {
+                *timer = HOT_RELOAD_INTERVAL;
+                self.reload_textures(rl, thread);
+            }
+        }
+
+        match self.state {
+            GameState::MainMenu => self.update_main_menu(input),
+            GameState::Playing => self.update_playing(rl, thread, inputs, dt)?,
+            GameState::Paused => self.update_paused(input),
+            GameState::Transition => self.update_transition(rl, thread, dt)?,
+            GameState::LevelComplete => self.update_level_complete(input),
+        }
+        Ok(())
+    }
+
+    // alpha is how far real time has run past the last update into the next
+    // one, from 0 up to but not including 1
+    pub fn render(&self, d: &mut RaylibDrawHandle, alpha: f32) {
+        d.clear_background(Color::get_color(BACKGROUND_COLOR));
+
+        match self.state {
+            GameState::MainMenu => self.draw_main_menu(d),
+            GameState::Playing => self.draw_playing(d, alpha),
+            GameState::Paused => self.draw_paused(d),
+            GameState::Transition => {
+                self.draw_playing(d, 1.0);
+                self.transition.draw(d);
+            }
+            GameState::LevelComplete => self.draw_level_complete(d),
+        }
+
+        if self.debug {
+            self.draw_debug_overlay(d);
+        }
+    }
+
+    // World space, so it has to be drawn inside begin_mode2D
+    fn draw_debug_collision(&self, d: &mut RaylibDrawHandle, view: Rectangle) {
+        let rects = self.world.obstacles.iter().copied()
+            .chain(self.players.iter().map(Player::bounds))
+            .chain(self.enemies.iter().map(Enemy::bounds))
+            .chain(self.players.iter().filter_map(Player::active_hitbox))
+            .filter(|rect| rect.check_collision_recs(&view));
+        for rect in rects {
+            d.draw_rectangle_lines_ex(rect, 1.0, self.debug_outline_color);
+        }
+        for enemy in &self.enemies {
+            let bounds = enemy.bounds();
+            let state = format!("{:?}", enemy.state());
+            d.draw_text(&state, bounds.x as i32, bounds.y as i32 - 10, 10, self.debug_outline_color);
+        }
+    }
+
+    fn draw_debug_overlay(&self, d: &mut RaylibDrawHandle) {
+        let player = &self.players[0];
+        let lines = [
+            format!("pos: {:.1}, {:.1}", player.pos.x, player.pos.y),
+            format!("animation: {:?}", player.current_animation_type()),
+            format!("frame: {}/{} ({:.0}%)", player.sprite.current_frame(), player.sprite.frame_count(), player.sprite.progress() * 100.0),
+            format!("tick: {} ({:.1}s)", self.world.clock.tick, self.world.clock.elapsed),
+        ];
+        let x = VIRTUAL_WIDTH - 220;
+        d.draw_fps(x, 10);
+        for (i, line) in lines.iter().enumerate() {
+            d.draw_text(line, x, 32 + i as i32 * 20, 16, Color::LIME);
+        }
+    }
+
+    fn update_main_menu(&mut self, input: &InputState) {
+        let count = MENU_OPTIONS.len();
+        if input.pressed_direction == Some(Direction::Up) {
+            self.menu_selection = (self.menu_selection + count - 1) % count;
+            self.audio.play("blip");
+        }
+        if input.pressed_direction == Some(Direction::Down) {
+            self.menu_selection = (self.menu_selection + 1) % count;
+            self.audio.play("blip");
+        }
+
+        if input.confirm_pressed {
+            match MENU_OPTIONS[self.menu_selection] {
+                MenuOption::Start => self.state = GameState::Playing,
+                MenuOption::Quit => self.quit = true,
+            }
+        }
+    }
+
+    fn draw_main_menu(&self, d: &mut RaylibDrawHandle) {
+        let h = VIRTUAL_HEIGHT;
+        draw_centered_text(d, "Non-Hot Reloaded Game", h / 2 - 80, 30, Color::WHITE);
+        for (i, option) in MENU_OPTIONS.iter().enumerate() {
+            let color = if i == self.menu_selection { Color::YELLOW } else { Color::GRAY };
+            draw_centered_text(d, &format!("{:?}", option), h / 2 + i as i32 * 30, 20, color);
+        }
+    }
+
+    // Only the closest NPC answers when several are in reach of pos
+    fn npc_in_range(&self, pos: Vector2) -> Option<usize> {
+        self.npcs.iter().enumerate()
+            .filter(|(_, npc)| npc.is_in_range(pos, INTERACT_RADIUS))
+            .min_by(|(_, a), (_, b)| a.center().distance_to(pos).total_cmp(&b.center().distance_to(pos)))
+            .map(|(i, _)| i)
+    }
+
+    // Box around the living players' centers, or everyone's once they're all
+    // dead, blended by alpha like the players are drawn
+    fn player_area(&self, alpha: f32) -> Rectangle {
+        let alive = self.players.iter().any(Player::is_alive);
+        let centers: Vec<Vector2> = self.players.iter()
+            .filter(|player| player.is_alive() || !alive)
+            .map(|player| player.render_center(alpha))
+            .collect();
+        let min = centers.iter().fold(centers[0], |min, c| Vector2::new(min.x.min(c.x), min.y.min(c.y)));
+        let max = centers.iter().fold(centers[0], |max, c| Vector2::new(max.x.max(c.x), max.y.max(c.y)));
+        Rectangle::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+
+    fn update_playing(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, inputs: &[InputState], dt: f32) -> Result<(), String> {
+        let idle = InputState::default();
+        let first = inputs.first().unwrap_or(&idle);
+        if first.pause_pressed {
+            self.state = GameState::Paused;
+            self.audio.pause_music();
+            return Ok(());
+        }
+        // Pausing returns first, so the clock stops along with everything else
+        self.world.clock.advance(dt);
+
+        if first.inventory_pressed {
+            self.inventory_open = !self.inventory_open;
+        }
+        if first.minimap_pressed {
+            self.minimap.visible = !self.minimap.visible;
+        }
+
+        // Talking holds every player still, the rest of the world carries on
+        let talking = self.dialogue.is_open();
+        if talking {
+            self.dialogue.update(dt);
+            if inputs.iter().any(|input| input.confirm_pressed || input.interact_pressed) {
+                self.dialogue.advance();
+            }
+        } else {
+            for (player, input) in self.players.iter().zip(inputs) {
+                if input.interact_pressed && player.is_alive() && let Some(i) = self.npc_in_range(player.center()) {
+                    let npc = &mut self.npcs[i];
+                    npc.face(player.center());
+                    self.dialogue.open(rl, &npc.dialogue);
+                    break;
+                }
+            }
+        }
+
+        self.tilemap.update(dt);
+        // Players block each other, so the others stand in as walls while
+        // each one moves
+        let walls = self.world.obstacles.len();
+        for i in 0..self.players.len() {
+            let others: Vec<Rectangle> = self.players.iter().enumerate()
+                .filter(|&(j, player)| j != i && player.is_alive() && can_collide(player.filter, self.players[i].filter))
+                .map(|(_, player)| player.bounds())
+                .collect();
+            self.world.obstacles.extend(others);
+            let input = if talking { &idle } else { inputs.get(i).unwrap_or(&idle) };
+            if let Some(cursor) = input.aim {
+                let target = self.screen_to_world(rl, i, cursor);
+                self.players[i].aim_at(target);
+            }
+            self.players[i].handle_input(input, &self.world, dt);
+            self.world.obstacles.truncate(walls);
+        }
+        let bodies: Vec<Rectangle> = self.players.iter()
+            .filter(|player| player.is_alive())
+            .map(Player::bounds)
+            .collect();
+        let mut actions = Vec::new();
+        for trigger in &mut self.triggers {
+            if trigger.update(&bodies) {
+                actions.push((trigger.action.clone(), trigger.rect));
+            }
+        }
+        for (action, rect) in actions {
+            self.run_trigger(rl, thread, action, rect)?;
+        }
+        for enemy in &mut self.enemies {
+            let center = enemy.center();
+            enemy.chase_target = self.players.iter()
+                .filter(|player| player.is_alive())
+                .map(Player::center)
+                .min_by(|a, b| a.distance_to(center).total_cmp(&b.distance_to(center)));
+            enemy.target_visible = enemy.chase_target
+                .is_some_and(|target| self.tilemap.has_line_of_sight(center, target));
+        }
+        let objects = self.players.iter_mut().map(|player| player as &mut dyn GameObject)
+            .chain(self.npcs.iter_mut().map(|npc| npc as &mut dyn GameObject))
+            .chain(self.enemies.iter_mut().map(|enemy| enemy as &mut dyn GameObject))
+            .chain(self.items.iter_mut().map(|item| item as &mut dyn GameObject))
+            .chain(self.projectiles.iter_mut().map(|projectile| projectile as &mut dyn GameObject));
+        for object in objects {
+            object.update(dt, &self.world);
+        }
+
+        // Spawned after the update so a shot isn't moved on the step it's fired
+        for (i, player) in self.players.iter().enumerate() {
+            if player.fired()
+                && let Some(projectile) = self.projectiles.acquire()
+            {
+                projectile.launch(player.center(), player.fire_direction(), i);
+            }
+        }
+        for player in &mut self.players {
+            if player.stepped() {
+                self.audio.play("footstep");
+            }
+            if player.dash_started() {
+                self.particles.spawn_burst(player.center(), 12, &mut self.rng);
+            }
+            for enemy in &self.enemies {
+                // Touching an enemy hurts as well as its swing does
+                let touching = enemy.bounds().check_collision_recs(&player.bounds());
+                let swung_at = enemy.active_hitbox().is_some_and(|hitbox| hitbox.check_collision_recs(&player.bounds()));
+                if enemy.is_alive() && !player.is_invulnerable() && (touching || swung_at) && can_collide(enemy.filter, player.filter) {
+                    player.take_damage(1);
+                    if let Some(text) = self.floating_texts.acquire() {
+                        text.show_damage(1, player.bounds(), PLAYER_DAMAGE_COLOR);
+                    }
+                    player.apply_knockback(player.center() - enemy.center(), KNOCKBACK_STRENGTH);
+                    for camera in &mut self.cameras {
+                        camera.trigger_shake(6.0, 0.25);
+                    }
+                }
+            }
+        }
+
+        self.enemy_grid.clear();
+        for (i, enemy) in self.enemies.iter().enumerate() {
+            self.enemy_grid.insert(i, enemy.bounds());
+        }
+        // Each swing lands at most once per enemy, however many frames the
+        // hitbox stays over it
+        for (i, player) in self.players.iter_mut().enumerate() {
+            let Some(hitbox) = player.active_hitbox() else {
+                continue;
+            };
+            let swing = (i, player.swing());
+            for j in self.enemy_grid.query(hitbox) {
+                let enemy = &mut self.enemies[j];
+                if enemy.is_alive() && enemy.last_hit_by != Some(swing) && can_collide(player.filter, enemy.filter)
+                    && hitbox.check_collision_recs(&enemy.bounds())
+                {
+                    enemy.last_hit_by = Some(swing);
+                    enemy.take_damage(1);
+                    if let Some(text) = self.floating_texts.acquire() {
+                        text.show_damage(1, enemy.bounds(), ENEMY_DAMAGE_COLOR);
+                    }
+                    enemy.apply_knockback(enemy.center() - player.center(), KNOCKBACK_STRENGTH);
+                    if !enemy.is_alive() {
+                        self.particles.spawn_burst(enemy.center(), 24, &mut self.rng);
+                        for camera in &mut self.cameras {
+                            camera.trigger_shake(4.0, 0.2);
+                        }
+                        award_kill(player, &mut self.particles, &mut self.rng, &self.audio);
+                        self.kills += 1;
+                    }
+                }
+            }
+        }
+        for projectile in self.projectiles.iter_mut().filter(|projectile| !projectile.spent) {
+            let hit = self.enemy_grid.query(projectile.bounds()).into_iter().find(|&j| {
+                let enemy = &self.enemies[j];
+                enemy.is_alive() && can_collide(projectile.filter, enemy.filter)
+                    && enemy.bounds().check_collision_recs(&projectile.bounds())
+            });
+            if let Some(j) = hit {
+                let enemy = &mut self.enemies[j];
+                projectile.spent = true;
+                enemy.take_damage(1);
+                if let Some(text) = self.floating_texts.acquire() {
+                    text.show_damage(1, enemy.bounds(), ENEMY_DAMAGE_COLOR);
+                }
+                enemy.apply_knockback(projectile.velocity, KNOCKBACK_STRENGTH / 2.0);
+                self.particles.spawn_burst(projectile.pos, 6, &mut self.rng);
+                if !enemy.is_alive() {
+                    self.particles.spawn_burst(enemy.center(), 24, &mut self.rng);
+                    self.kills += 1;
+                    if let Some(player) = self.players.get_mut(projectile.owner) {
+                        award_kill(player, &mut self.particles, &mut self.rng, &self.audio);
+                    }
+                }
+            }
+        }
+        self.projectiles.release_expired();
+        self.enemies.retain(|enemy| !enemy.can_be_removed());
+
+        for item in &mut self.items {
+            // A full inventory leaves the item where it is
+            for player in &mut self.players {
+                if item.overlaps(player.bounds()) && player.inventory.add_item(item.kind, 1) == 0 {
+                    item.collected = true;
+                    self.audio.play("coin");
+                }
+            }
+        }
+        self.particles.update(dt);
+        if let Some(weather) = &mut self.weather {
+            weather.update(dt);
+        }
+        for text in self.floating_texts.iter_mut() {
+            text.update(dt);
+        }
+        self.floating_texts.release_expired();
+        self.day_night.update(&self.world.clock);
+        self.lighting.update(dt);
+        if self.split_views.is_empty() {
+            let area = self.player_area(1.0);
+            self.cameras[0].frame(area, dt);
+        } else {
+            for (camera, player) in self.cameras.iter_mut().zip(&self.players) {
+                camera.follow(player.center(), dt);
+            }
+        }
+        self.reveal_minimap();
+
+        let progress = self.progress();
+        let mut completed = false;
+        for objective in &mut self.objectives {
+            completed |= objective.update(&progress);
+        }
+        if completed {
+            self.audio.play("levelup");
+            if self.objectives.iter().all(|objective| objective.complete) {
+                self.state = GameState::LevelComplete;
+            }
+        }
+
+        if self.players.iter().all(Player::death_finished) {
+            self.destination = Some(Destination::Respawn);
+            self.transition.start();
+            self.state = GameState::Transition;
+        }
+        Ok(())
+    }
+
+    fn progress(&self) -> Progress {
+        Progress {
+            coins: self.players.iter().map(|player| player.inventory.count(ItemKind::Coin)).sum(),
+            kills: self.kills,
+            checkpoint_reached: self.checkpoint.is_some(),
+        }
+    }
+
+    // Enemies are spawned in a row centered on the trigger, guarding the
+    // spot, each moved off anything solid the row runs into
+    fn run_trigger(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, action: TriggerAction, rect: Rectangle) -> Result<(), String> {
+        match action {
+            TriggerAction::Dialogue(text) => if !self.dialogue.is_open() {
+                self.dialogue.open(rl, &text);
+            },
+            TriggerAction::ChangeMap { map, spawn } => {
+                let level = LEVELS.into_iter().find(|level| level.map == map)
+                    .ok_or(format!("A door leads to map {}, which no level uses", map))?;
+                self.destination = Some(Destination::Door(level, spawn));
+                self.transition.start();
+                self.state = GameState::Transition;
+            }
+            TriggerAction::Checkpoint => {
+                self.checkpoint = Some(Checkpoint {
+                    level: self.level,
+                    positions: self.players.iter().map(Player::center).collect(),
+                    health: self.players.iter().map(|player| player.health).collect(),
+                });
+                if let Some(text) = self.floating_texts.acquire() {
+                    text.show("Checkpoint", Vector2::new(rect.x + rect.width / 2.0, rect.y), Color::SKYBLUE);
+                }
+                self.audio.play("blip");
+            }
+            TriggerAction::SpawnEnemies(count) => {
+                let center = Vector2::new(rect.x + rect.width / 2.0, rect.y + rect.height / 2.0);
+                for i in 0..count {
+                    let offset = (i as f32 - (count - 1) as f32 / 2.0) * ENEMY_SPACING;
+                    let Some(pos) = self.tilemap.nearest_open(center + Vector2::new(offset, 0.0)) else {
+                        break; // the map has nowhere open at all
+                    };
+                    self.enemies.push(spawn_enemy_around(rl, thread, &mut self.textures, pos, &mut self.rng)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Draws each split screen view into its texture for draw_playing to put
+    // on screen. Call before the frame's texture mode, they don't nest.
+    pub fn render_views(&mut self, d: &mut RaylibDrawHandle, thread: &RaylibThread, alpha: f32) {
+        if self.split_views.is_empty() || self.state == GameState::MainMenu {
+            return;
+        }
+
+        // Nothing steps outside of play, so there's nothing to blend between
+        let alpha = if self.state == GameState::Playing { alpha } else { 1.0 };
+        let mut views = std::mem::take(&mut self.split_views);
+        for (i, view) in views.iter_mut().enumerate() {
+            let size = Vector2::new(view.texture.width as f32, view.texture.height as f32);
+            let mut d = d.begin_texture_mode(thread, view);
+            d.clear_background(Color::get_color(BACKGROUND_COLOR));
+            let center = self.players[i].render_center(alpha);
+            self.draw_world(&mut d, &self.cameras[i].camera, size, Rectangle::new(center.x, center.y, 0.0, 0.0), alpha);
+        }
+        self.split_views = views;
+    }
+
+    // Everything seen through camera on a screen of size, lit around
+    // light_area when lighting is on
+    fn draw_world(&self, d: &mut RaylibDrawHandle, camera: &Camera2D, size: Vector2, light_area: Rectangle, alpha: f32) {
+        let view = camera_view(camera, size.x, size.y);
+        self.background.draw(d, camera);
+        {
+            let mut d2 = d.begin_mode2D(camera);
+            self.tilemap.draw(&mut d2, view);
+            let mut drawables: Vec<&dyn Drawable> = self.players.iter().map(|player| player as &dyn Drawable).collect();
+            drawables.extend(self.npcs.iter().map(|npc| npc as &dyn Drawable));
+            drawables.extend(self.enemies.iter().map(|enemy| enemy as &dyn Drawable));
+            drawables.extend(self.items.iter().map(|item| item as &dyn Drawable));
+            drawables.extend(self.projectiles.iter().map(|projectile| projectile as &dyn Drawable));
+            draw_by_depth(&mut drawables, &mut d2, view, alpha);
+            self.particles.draw(&mut d2, view);
+            for text in self.floating_texts.iter() {
+                text.draw(&mut d2, view);
+            }
+            if !self.dialogue.is_open() {
+                let mut in_range: Vec<usize> = self.players.iter()
+                    .filter(|player| player.is_alive())
+                    .filter_map(|player| self.npc_in_range(player.center()))
+                    .collect();
+                in_range.dedup();
+                for i in in_range {
+                    draw_interact_prompt(&mut d2, self.npcs[i].bounds());
+                }
+            }
+            if self.debug {
+                self.draw_debug_collision(&mut d2, view);
+            }
+        }
+
+        // One light wide enough for everyone, around the middle of the area
+        if self.lighting_enabled {
+            let middle = Vector2::new(light_area.x + light_area.width / 2.0, light_area.y + light_area.height / 2.0);
+            let center = d.get_world_to_screen2D(middle, camera);
+            let spread = Vector2::new(light_area.width, light_area.height).length() / 2.0 * camera.zoom;
+            self.lighting.draw_lighting(d, center, self.lighting.current_radius() + spread);
+        }
+        self.day_night.draw(d);
+    }
+
+    // Positions only change in update, so the players are drawn blended from
+    // the step before by alpha rather than snapping once per step. The
+    // cameras still follow the real positions.
+    fn draw_playing(&self, d: &mut RaylibDrawHandle, alpha: f32) {
+        if self.split_views.is_empty() {
+            let size = Vector2::new(VIRTUAL_WIDTH as f32, VIRTUAL_HEIGHT as f32);
+            self.draw_world(d, &self.cameras[0].camera, size, self.player_area(alpha), alpha);
+        } else {
+            let mut x = 0.0;
+            for view in &self.split_views {
+                let (w, h) = (view.texture.width as f32, view.texture.height as f32);
+                // Render textures are stored upside down, the negative height flips it back
+                d.draw_texture_rec(view, Rectangle::new(0.0, 0.0, w, -h), Vector2::new(x, 0.0), Color::WHITE);
+                x += w;
+            }
+            for i in 1..self.split_views.len() {
+                let x = (VIRTUAL_WIDTH * i as i32 / self.split_views.len() as i32) as f32;
+                d.draw_line_ex(Vector2::new(x, 0.0), Vector2::new(x, VIRTUAL_HEIGHT as f32), 2.0, Color::BLACK);
+            }
+        }
+
+        if let Some(weather) = &self.weather {
+            weather.draw(d);
+        }
+
+        // Each player's HUD in their own share of the top of the screen
+        let width = VIRTUAL_WIDTH as f32 / self.players.len() as f32;
+        for (i, player) in self.players.iter().enumerate() {
+            self.draw_hud(d, player, Vector2::new(i as f32 * width, 0.0));
+        }
+        self.draw_objectives(d);
+        let players: Vec<Vector2> = self.players.iter()
+            .filter(|player| player.is_alive())
+            .map(|player| player.render_center(alpha))
+            .collect();
+        let enemies: Vec<Vector2> = self.enemies.iter()
+            .filter(|enemy| enemy.is_alive())
+            .map(Enemy::center)
+            .collect();
+        self.minimap.draw(d, self.cameras[0].camera.target, &players, &enemies);
+        self.dialogue.draw(d);
+        if self.inventory_open {
+            self.draw_inventory(d);
+        }
+    }
+
+    fn draw_item_icon(&self, d: &mut RaylibDrawHandle, kind: ItemKind, dest: Rectangle) {
+        let icon = match kind {
+            ItemKind::Coin => &self.coin_icon,
+        };
+        let frame = icon.height as f32; // square frames in a row
+        d.draw_texture_pro(&**icon, Rectangle::new(0.0, 0.0, frame, frame), dest, Vector2::zero(), 0.0, Color::WHITE);
+    }
+
+    // Screen space, a grid of every slot whether or not it holds anything
+    fn draw_inventory(&self, d: &mut RaylibDrawHandle) {
+        let slots = self.players[0].inventory.slots();
+        let columns = HUD.inventory_columns;
+        let rows = slots.len().div_ceil(columns);
+        let step = HUD.slot_size + HUD.slot_gap;
+        let width = columns as f32 * step - HUD.slot_gap;
+        let height = rows as f32 * step - HUD.slot_gap;
+        let origin = Vector2::new((VIRTUAL_WIDTH as f32 - width) / 2.0, (VIRTUAL_HEIGHT as f32 - height) / 2.0);
+
+        let padding = HUD.slot_gap * 2.0;
+        let panel = Rectangle::new(origin.x - padding, origin.y - padding, width + padding * 2.0, height + padding * 2.0);
+        d.draw_rectangle_rec(panel, Color::BLACK.alpha(0.7));
+        for (i, slot) in slots.iter().enumerate() {
+            let rect = Rectangle::new(
+                origin.x + (i % columns) as f32 * step,
+                origin.y + (i / columns) as f32 * step,
+                HUD.slot_size,
+                HUD.slot_size,
+            );
+            d.draw_rectangle_rec(rect, Color::DARKGRAY.alpha(0.8));
+            d.draw_rectangle_lines_ex(rect, 1.0, Color::GRAY);
+            if let Some(slot) = slot {
+                let inset = HUD.slot_size * 0.15;
+                let dest = Rectangle::new(rect.x + inset, rect.y + inset, rect.width - inset * 2.0, rect.height - inset * 2.0);
+                self.draw_item_icon(d, slot.kind, dest);
+                let count = slot.count.to_string();
+                let text_width = d.measure_text(&count, 10);
+                d.draw_text(&count, (rect.x + rect.width) as i32 - text_width - 3, (rect.y + rect.height) as i32 - 12, 10, Color::WHITE);
+            }
+        }
+    }
+
+    // Screen space, call outside of begin_mode2D. The layout is laid out from
+    // origin rather than the corner of the screen.
+    fn draw_hud(&self, d: &mut RaylibDrawHandle, player: &Player, origin: Vector2) {
+        let bar = HUD.health_bar;
+        player.draw_health_bar(d, Rectangle::new(origin.x + bar.x, origin.y + bar.y, bar.width, bar.height));
+
+        let icon = Rectangle::new(origin.x + HUD.coins.x, origin.y + HUD.coins.y, HUD.icon_size, HUD.icon_size);
+        self.draw_item_icon(d, ItemKind::Coin, icon);
+        let font_size = HUD.icon_size as i32;
+        let coins = player.inventory.count(ItemKind::Coin);
+        d.draw_text(&coins.to_string(), (icon.x + icon.width + 6.0) as i32, icon.y as i32, font_size, Color::WHITE);
+
+        let level = format!("Lv {}  {}/{}", player.level, player.xp, player.xp_to_next());
+        d.draw_text(&level, (origin.x + HUD.level.x) as i32, (origin.y + HUD.level.y) as i32, 10, Color::WHITE);
+    }
+
+    // A box per objective, ticked once it's done
+    fn draw_objectives(&self, d: &mut RaylibDrawHandle) {
+        let progress = self.progress();
+        let size = 8.0;
+        for (i, objective) in self.objectives.iter().enumerate() {
+            let pos = HUD.objectives + Vector2::new(0.0, i as f32 * HUD.objective_spacing);
+            let check = Rectangle::new(pos.x, pos.y + 1.0, size, size);
+            d.draw_rectangle_lines_ex(check, 1.0, Color::WHITE);
+            let color = if objective.complete {
+                d.draw_line_ex(Vector2::new(check.x + 1.0, check.y + 4.0), Vector2::new(check.x + 3.5, check.y + 7.0), 1.5, Color::GREEN);
+                d.draw_line_ex(Vector2::new(check.x + 3.5, check.y + 7.0), Vector2::new(check.x + 8.0, check.y - 1.0), 1.5, Color::GREEN);
+                Color::GRAY
+            } else {
+                Color::WHITE
+            };
+            let text = objective.description(&progress);
+            d.draw_text(&text, (pos.x + size + 6.0) as i32, pos.y as i32, 10, color);
+        }
+    }
+
+    fn update_level_complete(&mut self, input: &InputState) {
+        if input.confirm_pressed {
+            self.state = GameState::Playing;
+        }
+    }
+
+    fn draw_level_complete(&self, d: &mut RaylibDrawHandle) {
+        self.draw_playing(d, 1.0);
+        draw_dim_overlay(d);
+        draw_centered_text(d, "LEVEL COMPLETE", VIRTUAL_HEIGHT / 2 - 30, 40, Color::GOLD);
+        draw_centered_text(d, "Press Enter to keep exploring", VIRTUAL_HEIGHT / 2 + 20, 10, Color::WHITE);
+    }
+
+    fn update_paused(&mut self, input: &InputState) {
+        if input.pause_pressed {
+            self.state = GameState::Playing;
+            self.audio.resume_music();
+        }
+    }
+
+    // Nothing steps while paused, so there's nothing to blend between
+    fn draw_paused(&self, d: &mut RaylibDrawHandle) {
+        self.draw_playing(d, 1.0);
+        draw_dim_overlay(d);
+        draw_centered_text(d, "PAUSED", VIRTUAL_HEIGHT / 2 - 20, 40, Color::WHITE);
+    }
+
+    // The world stands still behind the fade
+    fn update_transition(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, dt: f32) -> Result<(), String> {
+        if self.transition.update(dt) {
+            match self.destination.take() {
+                Some(Destination::Door(level, spawn)) => self.load_level(rl, thread, level, &spawn)?,
+                Some(Destination::Respawn) => self.respawn(rl, thread)?,
+                None => {}
+            }
+        }
+        if self.transition.is_finished() {
+            self.state = GameState::Playing;
+        }
+        Ok(())
+    }
+
+    // Everyone comes back at the last checkpoint, in its level, or at full
+    // health where they entered this level if there isn't one yet. They each
+    // lose RESPAWN_COST coins, or what they have if it's fewer. The rest of
+    // what they carry and the world around them stay as they were, a
+    // checkpoint in another level brings that back as it was left.
+    fn respawn(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) -> Result<(), String> {
+        let (positions, health) = match self.checkpoint.clone() {
+            Some(checkpoint) => {
+                if checkpoint.level.map != self.level.map {
+                    self.load_level(rl, thread, checkpoint.level, "player")?;
+                }
+                (checkpoint.positions, checkpoint.health)
+            }
+            None => (self.player_spawns.clone(), self.players.iter().map(|player| player.max_health).collect()),
+        };
+        for ((player, &pos), &health) in self.players.iter_mut().zip(&positions).zip(&health) {
+            player.revive(health);
+            player.teleport(pos);
+            let cost = player.inventory.count(ItemKind::Coin).min(RESPAWN_COST);
+            player.inventory.remove_item(ItemKind::Coin, cost);
+        }
+        self.projectiles.clear();
+        self.snap_cameras();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coin(x: f32) -> Item {
+        Item::new(ItemKind::Coin, Vector2::new(x, 0.0), AnimatedSprite::new(1.0))
+    }
+
+    fn grunt(x: f32) -> Enemy {
+        let pos = Vector2::new(x, 0.0);
+        Enemy::new(pos, pos, 90.0, AnimatedSprite::new(1.0), HERO_COLLISION)
+    }
+
+    fn zone() -> Trigger {
+        Trigger::new(Rectangle::new(0.0, 0.0, 32.0, 32.0), true, TriggerAction::Checkpoint)
+    }
+
+    // What loading a level spawns afresh from its map
+    fn fresh() -> (Vec<Enemy>, Vec<Item>, Vec<Trigger>) {
+        (vec![grunt(10.0), grunt(20.0)], vec![coin(10.0), coin(20.0), coin(30.0)], vec![zone(), zone()])
+    }
+
+    #[test]
+    fn a_level_comes_back_as_it_was_left() {
+        let (mut enemies, mut items, mut triggers) = fresh();
+        items[1].collected = true;
+        enemies.remove(0);
+        triggers[1].fired = true;
+        let left = LeftBehind::take(&mut enemies, &mut items, &triggers);
+        assert!(enemies.is_empty() && items.is_empty());
+
+        let (mut enemies, mut items, mut triggers) = fresh();
+        left.put_back(&mut enemies, &mut items, &mut triggers);
+        assert_eq!(items.iter().map(|item| item.pos.x).collect::<Vec<_>>(), vec![10.0, 30.0]);
+        assert!(items.iter().all(|item| !item.collected));
+        assert_eq!(enemies.iter().map(|enemy| enemy.pos.x).collect::<Vec<_>>(), vec![20.0]);
+        assert_eq!(triggers.iter().map(|trigger| trigger.fired).collect::<Vec<_>>(), vec![false, true]);
+    }
+
+    #[test]
+    fn triggers_start_over_when_the_map_has_changed() {
+        let (mut enemies, mut items, mut triggers) = fresh();
+        triggers[0].fired = true;
+        let left = LeftBehind::take(&mut enemies, &mut items, &triggers);
+
+        let mut changed = vec![zone(), zone(), zone()];
+        left.put_back(&mut enemies, &mut items, &mut changed);
+        assert!(changed.iter().all(|trigger| !trigger.fired));
+    }
+
+    #[test]
+    fn respawning_in_the_checkpoints_level_keeps_what_was_collected() {
+        let mut left_behind = HashMap::new();
+
+        // Coins taken and an enemy beaten where the checkpoint is, then
+        // through a door and killed in the next level
+        let (mut enemies, mut items, triggers) = fresh();
+        items[0].collected = true;
+        enemies.remove(1);
+        left_behind.insert(FIRST_LEVEL.map, LeftBehind::take(&mut enemies, &mut items, &triggers));
+        let (mut enemies, mut items, triggers) = fresh();
+        items[2].collected = true;
+
+        // Respawning walks back into the checkpoint's level like load_level does
+        left_behind.insert(CAVE_LEVEL.map, LeftBehind::take(&mut enemies, &mut items, &triggers));
+        let (mut enemies, mut items, mut triggers) = fresh();
+        left_behind.remove(FIRST_LEVEL.map).unwrap().put_back(&mut enemies, &mut items, &mut triggers);
+        assert_eq!(items.iter().map(|item| item.pos.x).collect::<Vec<_>>(), vec![20.0, 30.0]);
+        assert_eq!(enemies.len(), 1);
+        assert_eq!(left_behind[CAVE_LEVEL.map].items.len(), 2);
+    }
+}
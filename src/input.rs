@@ -0,0 +1,244 @@
+use raylib::prelude::*;
+
+use crate::animation::Direction;
+
+pub struct KeyBindings {
+    pub up: KeyboardKey,
+    pub down: KeyboardKey,
+    pub left: KeyboardKey,
+    pub right: KeyboardKey,
+    pub attack: KeyboardKey,
+    pub fire: KeyboardKey,
+    pub dash: KeyboardKey,
+    pub pause: [KeyboardKey; 2],
+    pub confirm: KeyboardKey,
+    pub mute: KeyboardKey,
+    pub debug: KeyboardKey,
+    pub inventory: KeyboardKey,
+    pub minimap: KeyboardKey,
+    pub interact: KeyboardKey,
+    pub fullscreen: KeyboardKey,
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        KeyBindings {
+            up: KeyboardKey::KEY_W,
+            down: KeyboardKey::KEY_S,
+            left: KeyboardKey::KEY_A,
+            right: KeyboardKey::KEY_D,
+            attack: KeyboardKey::KEY_J,
+            fire: KeyboardKey::KEY_K,
+            dash: KeyboardKey::KEY_LEFT_SHIFT,
+            pause: [KeyboardKey::KEY_ESCAPE, KeyboardKey::KEY_P],
+            confirm: KeyboardKey::KEY_ENTER,
+            mute: KeyboardKey::KEY_M,
+            debug: KeyboardKey::KEY_F3,
+            inventory: KeyboardKey::KEY_I,
+            minimap: KeyboardKey::KEY_TAB,
+            interact: KeyboardKey::KEY_E,
+            fullscreen: KeyboardKey::KEY_F11,
+        }
+    }
+}
+
+impl KeyBindings {
+    // A second player on the same keyboard. Only the keys a player acts with
+    // differ, pausing and the like always come from the first player.
+    pub fn arrows() -> KeyBindings {
+        KeyBindings {
+            up: KeyboardKey::KEY_UP,
+            down: KeyboardKey::KEY_DOWN,
+            left: KeyboardKey::KEY_LEFT,
+            right: KeyboardKey::KEY_RIGHT,
+            attack: KeyboardKey::KEY_PERIOD,
+            fire: KeyboardKey::KEY_COMMA,
+            dash: KeyboardKey::KEY_RIGHT_SHIFT,
+            interact: KeyboardKey::KEY_SLASH,
+            ..KeyBindings::default()
+        }
+    }
+}
+
+// Movement is always the left stick and d-pad
+pub struct GamepadBindings {
+    pub id: i32, // which connected controller, from 0
+    pub attack: GamepadButton,
+    pub fire: GamepadButton,
+    pub dash: GamepadButton,
+    pub pause: GamepadButton,
+    pub confirm: GamepadButton,
+    pub inventory: GamepadButton,
+    pub interact: GamepadButton,
+    pub deadzone: f32, // stick deflection below this counts as centered
+}
+
+impl Default for GamepadBindings {
+    fn default() -> GamepadBindings {
+        GamepadBindings {
+            id: 0,
+            attack: GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN,
+            fire: GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_UP,
+            dash: GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT,
+            pause: GamepadButton::GAMEPAD_BUTTON_MIDDLE_RIGHT,
+            confirm: GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_DOWN,
+            inventory: GamepadButton::GAMEPAD_BUTTON_MIDDLE_LEFT,
+            interact: GamepadButton::GAMEPAD_BUTTON_RIGHT_FACE_LEFT,
+            deadzone: 0.2,
+        }
+    }
+}
+
+// Keyboard and gamepad read together, so either can be used at any time and
+// the gamepad is simply ignored while none is connected
+#[derive(Default)]
+pub struct InputBindings {
+    pub keys: KeyBindings,
+    pub gamepad: GamepadBindings,
+    pub mouse: bool, // the cursor aims, left click attacks and right click fires
+}
+
+impl InputBindings {
+    // Each player after the first gets the arrow keys and the next controller
+    pub fn for_player(index: usize) -> InputBindings {
+        let keys = if index == 0 { KeyBindings::default() } else { KeyBindings::arrows() };
+        InputBindings {
+            keys,
+            gamepad: GamepadBindings { id: index as i32, ..GamepadBindings::default() },
+            mouse: index == 0,
+        }
+    }
+
+    fn gamepad_connected(&self, rl: &RaylibHandle) -> bool {
+        rl.is_gamepad_available(self.gamepad.id)
+    }
+
+    fn directions(&self) -> [(KeyboardKey, GamepadButton, Direction); 4] {
+        [
+            (self.keys.left, GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_LEFT, Direction::Left),
+            (self.keys.right, GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_RIGHT, Direction::Right),
+            (self.keys.down, GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_DOWN, Direction::Down),
+            (self.keys.up, GamepadButton::GAMEPAD_BUTTON_LEFT_FACE_UP, Direction::Up),
+        ]
+    }
+
+    // Digital input gives whole steps on each axis, the stick keeps its
+    // analog magnitude. Callers normalize anything longer than 1.
+    fn movement(&self, rl: &RaylibHandle) -> Vector2 {
+        let pad = self.gamepad_connected(rl);
+        let mut movement = Vector2::zero();
+        for (key, button, dir) in self.directions() {
+            if rl.is_key_down(key) || (pad && rl.is_gamepad_button_down(self.gamepad.id, button)) {
+                movement += dir.to_vector();
+            }
+        }
+
+        if pad {
+            let stick = Vector2::new(
+                rl.get_gamepad_axis_movement(self.gamepad.id, GamepadAxis::GAMEPAD_AXIS_LEFT_X),
+                rl.get_gamepad_axis_movement(self.gamepad.id, GamepadAxis::GAMEPAD_AXIS_LEFT_Y),
+            );
+            if stick.length() > self.gamepad.deadzone {
+                movement += stick;
+            }
+        }
+        movement
+    }
+
+    // The most recent digital direction pressed this frame, if any
+    fn pressed_direction(&self, rl: &RaylibHandle) -> Option<Direction> {
+        let pad = self.gamepad_connected(rl);
+        let mut pressed = None;
+        for (key, button, dir) in self.directions() {
+            if rl.is_key_pressed(key) || (pad && rl.is_gamepad_button_pressed(self.gamepad.id, button)) {
+                pressed = Some(dir);
+            }
+        }
+        pressed
+    }
+
+    fn pressed(&self, rl: &RaylibHandle, key: KeyboardKey, button: Option<GamepadButton>) -> bool {
+        rl.is_key_pressed(key)
+            || button.is_some_and(|button| self.gamepad_connected(rl) && rl.is_gamepad_button_pressed(self.gamepad.id, button))
+    }
+
+    fn clicked(&self, rl: &RaylibHandle, button: MouseButton) -> bool {
+        self.mouse && rl.is_mouse_button_pressed(button)
+    }
+
+    // The mouse is mapped to the virtual screen (see Letterbox::map_mouse)
+    fn aim(&self, rl: &RaylibHandle) -> Option<Vector2> {
+        (self.mouse && rl.is_cursor_on_screen()).then(|| rl.get_mouse_position())
+    }
+}
+
+// Everything the game reacts to from the player for one frame, polled once so
+// nothing else has to know which keys or buttons produced it
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InputState {
+    pub move_dir: Vector2, // can be longer than 1 on diagonals
+    pub pressed_direction: Option<Direction>, // most recent digital direction pressed this frame
+    pub aim: Option<Vector2>, // the cursor in virtual screen pixels, while it's over the window
+    pub attack_pressed: bool,
+    pub fire_pressed: bool,
+    pub dash_pressed: bool,
+    pub pause_pressed: bool,
+    pub confirm_pressed: bool,
+    pub mute_pressed: bool,
+    pub debug_pressed: bool,
+    pub inventory_pressed: bool,
+    pub minimap_pressed: bool,
+    pub interact_pressed: bool,
+    pub fullscreen_pressed: bool, // handled by the window, not the game
+}
+
+impl InputState {
+    pub fn poll(rl: &RaylibHandle, bindings: &InputBindings) -> InputState {
+        let keys = &bindings.keys;
+        let pad = &bindings.gamepad;
+        InputState {
+            move_dir: bindings.movement(rl),
+            pressed_direction: bindings.pressed_direction(rl),
+            aim: bindings.aim(rl),
+            attack_pressed: bindings.pressed(rl, keys.attack, Some(pad.attack))
+                || bindings.clicked(rl, MouseButton::MOUSE_BUTTON_LEFT),
+            fire_pressed: bindings.pressed(rl, keys.fire, Some(pad.fire))
+                || bindings.clicked(rl, MouseButton::MOUSE_BUTTON_RIGHT),
+            dash_pressed: bindings.pressed(rl, keys.dash, Some(pad.dash)),
+            pause_pressed: bindings.pressed(rl, keys.pause[0], Some(pad.pause))
+                || bindings.pressed(rl, keys.pause[1], None),
+            confirm_pressed: bindings.pressed(rl, keys.confirm, Some(pad.confirm)),
+            mute_pressed: bindings.pressed(rl, keys.mute, None),
+            debug_pressed: bindings.pressed(rl, keys.debug, None),
+            inventory_pressed: bindings.pressed(rl, keys.inventory, Some(pad.inventory)),
+            minimap_pressed: bindings.pressed(rl, keys.minimap, None),
+            interact_pressed: bindings.pressed(rl, keys.interact, Some(pad.interact)),
+            fullscreen_pressed: bindings.pressed(rl, keys.fullscreen, None),
+        }
+    }
+
+    // Folds a newer poll into this one. Held movement and aim are replaced, presses
+    // are kept until clear_pressed so a frame that runs no update doesn't
+    // lose them.
+    pub fn merge(&mut self, newer: &InputState) {
+        self.move_dir = newer.move_dir;
+        self.aim = newer.aim;
+        self.pressed_direction = newer.pressed_direction.or(self.pressed_direction);
+        self.attack_pressed |= newer.attack_pressed;
+        self.fire_pressed |= newer.fire_pressed;
+        self.dash_pressed |= newer.dash_pressed;
+        self.pause_pressed |= newer.pause_pressed;
+        self.confirm_pressed |= newer.confirm_pressed;
+        self.mute_pressed |= newer.mute_pressed;
+        self.debug_pressed |= newer.debug_pressed;
+        self.inventory_pressed |= newer.inventory_pressed;
+        self.minimap_pressed |= newer.minimap_pressed;
+        self.interact_pressed |= newer.interact_pressed;
+        self.fullscreen_pressed |= newer.fullscreen_pressed;
+    }
+
+    // Once an update has seen a press, later updates in the same frame shouldn't
+    pub fn clear_pressed(&mut self) {
+        *self = InputState { move_dir: self.move_dir, aim: self.aim, ..InputState::default() };
+    }
+}
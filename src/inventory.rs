@@ -0,0 +1,151 @@
+pub const INVENTORY_SLOTS: usize = 12;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ItemKind {
+    Coin,
+}
+
+impl ItemKind {
+    pub fn from_name(name: &str) -> Option<ItemKind> {
+        match name {
+            "coin" => Some(Self::Coin),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Coin => "coin",
+        }
+    }
+
+    // Most of this kind one slot holds
+    pub fn max_stack(self) -> u32 {
+        match self {
+            Self::Coin => 99,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Slot {
+    pub kind: ItemKind,
+    pub count: u32, // never 0, an emptied slot becomes None
+}
+
+pub struct Inventory {
+    slots: [Option<Slot>; INVENTORY_SLOTS],
+}
+
+impl Inventory {
+    pub fn new() -> Inventory {
+        Inventory { slots: [None; INVENTORY_SLOTS] }
+    }
+
+    pub fn slots(&self) -> &[Option<Slot>] {
+        &self.slots
+    }
+
+    pub fn count(&self, kind: ItemKind) -> u32 {
+        self.slots.iter().flatten()
+            .filter(|slot| slot.kind == kind)
+            .map(|slot| slot.count)
+            .sum()
+    }
+
+    // Tops up slots already holding the kind first, then starts new ones in
+    // the first empty slots. Returns how many didn't fit.
+    pub fn add_item(&mut self, kind: ItemKind, count: u32) -> u32 {
+        let mut remaining = count;
+        for slot in self.slots.iter_mut().flatten().filter(|slot| slot.kind == kind) {
+            let added = remaining.min(kind.max_stack() - slot.count);
+            slot.count += added;
+            remaining -= added;
+        }
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if remaining == 0 {
+                break;
+            }
+            let added = remaining.min(kind.max_stack());
+            *slot = Some(Slot { kind, count: added });
+            remaining -= added;
+        }
+        remaining
+    }
+
+    // All or nothing: false, leaving the inventory as it was, if there
+    // aren't count of the kind. Takes from the last slots first so the
+    // first stays the fullest.
+    pub fn remove_item(&mut self, kind: ItemKind, count: u32) -> bool {
+        if self.count(kind) < count {
+            return false;
+        }
+
+        let mut remaining = count;
+        for entry in self.slots.iter_mut().rev() {
+            if remaining == 0 {
+                break;
+            }
+            if let Some(slot) = entry
+                && slot.kind == kind
+            {
+                let removed = remaining.min(slot.count);
+                slot.count -= removed;
+                remaining -= removed;
+                if slot.count == 0 {
+                    *entry = None;
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_stack_into_slots_already_holding_the_kind() {
+        let mut inventory = Inventory::new();
+        assert_eq!(inventory.add_item(ItemKind::Coin, 40), 0);
+        assert_eq!(inventory.add_item(ItemKind::Coin, 40), 0);
+        assert_eq!(inventory.slots()[0], Some(Slot { kind: ItemKind::Coin, count: 80 }));
+        assert_eq!(inventory.slots()[1], None);
+
+        // Past a full stack the rest starts the next slot
+        assert_eq!(inventory.add_item(ItemKind::Coin, 30), 0);
+        assert_eq!(inventory.slots()[0], Some(Slot { kind: ItemKind::Coin, count: 99 }));
+        assert_eq!(inventory.slots()[1], Some(Slot { kind: ItemKind::Coin, count: 11 }));
+    }
+
+    #[test]
+    fn a_full_inventory_returns_the_overflow() {
+        let mut inventory = Inventory::new();
+        let capacity = ItemKind::Coin.max_stack() * INVENTORY_SLOTS as u32;
+        assert_eq!(inventory.add_item(ItemKind::Coin, capacity + 5), 5);
+        assert_eq!(inventory.count(ItemKind::Coin), capacity);
+        assert_eq!(inventory.add_item(ItemKind::Coin, 1), 1);
+    }
+
+    #[test]
+    fn removing_too_many_leaves_it_untouched() {
+        let mut inventory = Inventory::new();
+        inventory.add_item(ItemKind::Coin, 10);
+        assert!(!inventory.remove_item(ItemKind::Coin, 11));
+        assert_eq!(inventory.count(ItemKind::Coin), 10);
+    }
+
+    #[test]
+    fn removing_empties_slots_from_the_back() {
+        let mut inventory = Inventory::new();
+        inventory.add_item(ItemKind::Coin, 120);
+        assert!(inventory.remove_item(ItemKind::Coin, 30));
+        assert_eq!(inventory.slots()[0], Some(Slot { kind: ItemKind::Coin, count: 90 }));
+        assert_eq!(inventory.slots()[1], None);
+
+        assert!(inventory.remove_item(ItemKind::Coin, 90));
+        assert!(inventory.slots().iter().all(Option::is_none));
+        assert!(inventory.remove_item(ItemKind::Coin, 0));
+    }
+}
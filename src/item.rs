@@ -0,0 +1,77 @@
+use raylib::prelude::*;
+
+use crate::animation::AnimatedSprite;
+use crate::drawable::Drawable;
+use crate::inventory::ItemKind;
+use crate::object::GameObject;
+use crate::world::World;
+
+// Something lying on the map for the player to pick up
+pub struct Item {
+    pub kind: ItemKind,
+    pub pos: Vector2,
+    pub sprite: AnimatedSprite,
+    pub collected: bool, // picked up, no longer drawn or collectable
+}
+
+impl Item {
+    pub fn new(kind: ItemKind, pos: Vector2, sprite: AnimatedSprite) -> Item {
+        Item { kind, pos, sprite, collected: false }
+    }
+
+    // Whether something with these bounds is touching the item and can
+    // still pick it up
+    pub fn overlaps(&self, bounds: Rectangle) -> bool {
+        !self.collected && self.bounds().check_collision_recs(&bounds)
+    }
+}
+
+impl Drawable for Item {
+    fn depth(&self) -> f32 {
+        let bounds = self.sprite.bounds(self.pos);
+        bounds.y + bounds.height
+    }
+
+    fn render(&self, d: &mut RaylibDrawHandle, view: Rectangle, _alpha: f32) {
+        if !self.collected {
+            self.sprite.draw(self.pos, view, d);
+        }
+    }
+}
+
+impl GameObject for Item {
+    fn update(&mut self, dt: f32, _world: &World) {
+        if !self.collected {
+            self.sprite.animate(dt);
+        }
+    }
+
+    fn bounds(&self) -> Rectangle {
+        self.sprite.bounds(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::{AnimationType, Direction, SpriteAnimation};
+    use crate::textures::blank_texture;
+
+    // A 16 pixel square coin with its top left at pos
+    fn coin(pos: Vector2) -> Item {
+        let mut sprite = AnimatedSprite::new(1.0);
+        sprite.add_animation(AnimationType::Idle(Direction::Down), SpriteAnimation::new(blank_texture(64, 16), 4, 10, true));
+        Item::new(ItemKind::Coin, pos, sprite)
+    }
+
+    #[test]
+    fn overlap_needs_touching_and_uncollected() {
+        let mut item = coin(Vector2::new(100.0, 100.0));
+        assert!(item.overlaps(Rectangle::new(110.0, 110.0, 20.0, 20.0)));
+        assert!(!item.overlaps(Rectangle::new(120.0, 100.0, 20.0, 20.0)));
+        assert!(!item.overlaps(Rectangle::new(0.0, 0.0, 20.0, 20.0)));
+
+        item.collected = true;
+        assert!(!item.overlaps(Rectangle::new(110.0, 110.0, 20.0, 20.0)));
+    }
+}
@@ -0,0 +1,65 @@
+use raylib::prelude::*;
+
+use crate::screen::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
+const GRADIENT_SIZE: i32 = 256;
+const DARKNESS: Color = Color::new(60, 60, 80, 255); // what the world is multiplied by away from the light
+
+// A lit circle around one point with the rest of the screen darkened,
+// multiplied over what's already been drawn
+pub struct Lighting {
+    gradient: Texture2D, // white at the center fading out to DARKNESS
+    pub radius: f32, // screen pixels
+    pub pulse: f32, // fraction of the radius it grows and shrinks by, 0 for a steady light
+    pub pulse_speed: f32, // radians per second
+    time: f32,
+}
+
+impl Lighting {
+    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread, radius: f32) -> Result<Lighting, String> {
+        let image = Image::gen_image_gradient_radial(GRADIENT_SIZE, GRADIENT_SIZE, 0.0, Color::WHITE, DARKNESS);
+        let gradient = rl.load_texture_from_image(thread, &image)
+            .map_err(|e| format!("Couldn't create the light texture: {}", e))?;
+        Ok(Lighting {
+            gradient,
+            radius,
+            pulse: 0.05,
+            pulse_speed: 3.0,
+            time: 0.0,
+        })
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    pub fn current_radius(&self) -> f32 {
+        self.radius * (1.0 + self.pulse * (self.time * self.pulse_speed).sin())
+    }
+
+    // Screen space, after the world and before the HUD
+    pub fn draw_lighting(&self, d: &mut RaylibDrawHandle, player_screen_pos: Vector2, radius: f32) {
+        let light = Rectangle::new(player_screen_pos.x - radius, player_screen_pos.y - radius, radius * 2.0, radius * 2.0);
+        let (w, h) = (VIRTUAL_WIDTH as f32, VIRTUAL_HEIGHT as f32);
+        let size = GRADIENT_SIZE as f32;
+
+        let mut d = d.begin_blend_mode(BlendMode::BLEND_MULTIPLIED);
+        d.draw_texture_pro(&self.gradient, Rectangle::new(0.0, 0.0, size, size), light, Vector2::zero(), 0.0, Color::WHITE);
+
+        // Everything around the light's square, above, below, then either side
+        let top = light.y.clamp(0.0, h);
+        let bottom = (light.y + light.height).clamp(0.0, h);
+        let left = light.x.clamp(0.0, w);
+        let right = (light.x + light.width).clamp(0.0, w);
+        for rect in [
+            Rectangle::new(0.0, 0.0, w, top),
+            Rectangle::new(0.0, bottom, w, h - bottom),
+            Rectangle::new(0.0, top, left, bottom - top),
+            Rectangle::new(right, top, w - right, bottom - top),
+        ] {
+            if rect.width > 0.0 && rect.height > 0.0 {
+                d.draw_rectangle_rec(rect, DARKNESS);
+            }
+        }
+    }
+}
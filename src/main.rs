@@ -1,300 +1,194 @@
-use std::collections::HashMap;
+mod animation;
+mod animation_clock;
+mod animation_controller;
+mod audio;
+mod camera;
+mod config;
+mod cooldown;
+mod daynight;
+mod dialogue;
+mod drawable;
+mod enemy;
+mod floating_text;
+mod game;
+mod input;
+mod inventory;
+mod item;
+mod json;
+mod lighting;
+mod minimap;
+mod npc;
+mod object;
+mod objective;
+mod parallax;
+mod particles;
+mod pathfinding;
+mod physics;
+mod player;
+mod pool;
+mod projectile;
+mod replay;
+mod rng;
+mod save;
+mod screen;
+mod spatial_hash;
+mod spawn;
+mod textures;
+mod tiled;
+mod tilemap;
+mod transition;
+mod trigger;
+mod weather;
+mod world;
 
 use raylib::prelude::*;
 
-struct SpriteAnimation {
-    texture: Texture2D,
-    frame_width: f32,
-    num_frames: u32,
-    current_frame: u32,
-    frames_counter: u32,
-    anim_speed: u32, // REVERSED
-}
-
-impl SpriteAnimation {
-    fn new(sprite: Texture2D, num_frames: u32, speed: u32) -> SpriteAnimation {
-        let frame_width = sprite.width as f32 / num_frames as f32;
-        SpriteAnimation { 
-            texture: sprite, 
-            frame_width,
-            num_frames, 
-            current_frame: 0, 
-            frames_counter: 0, 
-            anim_speed: speed, 
-        }
-    }
-
-    fn reset(&mut self) {
-        self.current_frame = 0;
-        self.frames_counter = 0;
-    }
-
-    fn animate(&mut self) {
-        self.frames_counter += 1;
-        if self.frames_counter >= (60 / self.anim_speed) {
-            self.frames_counter = 0;
-            self.current_frame += 1;
-
-            if self.current_frame > self.num_frames - 1 {
-                self.current_frame = 0;
+use config::Config;
+use game::{FIXED_DT, Game};
+use input::InputState;
+use replay::{Recorder, Replay};
+use screen::{Letterbox, VIRTUAL_HEIGHT, VIRTUAL_WIDTH, WindowMode};
+
+// Optional --record <file> or --replay <file>
+fn parse_args() -> Result<(Option<Recorder>, Option<Replay>), String> {
+    let mut recorder = None;
+    let mut replay = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--record" => {
+                let path = args.next().ok_or("--record needs a file")?;
+                recorder = Some(Recorder::create(&path)?);
             }
+            "--replay" => {
+                let path = args.next().ok_or("--replay needs a file")?;
+                replay = Some(Replay::load(&path)?);
+            }
+            other => return Err(format!("Unknown argument {}, expected --record <file> or --replay <file>", other)),
         }
     }
-
-    fn draw(&self, pos: Vector2, d: &mut RaylibDrawHandle) {
-        let source_rec = Rectangle::new(
-            self.current_frame as f32 * self.frame_width, 
-            0.0, 
-            self.frame_width, 
-            self.texture.height as f32
-        );
-
-        let dest_rec = Rectangle::new(pos.x, pos.y, self.frame_width * 1.5, self.texture.height as f32 * 1.5);
-        d.draw_texture_pro(
-            &self.texture,
-            source_rec,
-            dest_rec,
-            Vector2::new(0.0, 0.0), // Origin (for rotation/scaling)
-            0.0,                    // Rotation
-            Color::WHITE,
-        );
-    }
-}
-
-#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
-enum AnimationType {
-    Idle(Direction),
-    Run(Direction),
-    Attack1(Direction),
-    Attack2(Direction),
-}
-
-impl AnimationType {
-    fn direction(&self) -> Direction {
-        match self {
-            Self::Idle(d) | Self::Run(d) | Self::Attack1(d) | Self::Attack2(d) => *d,
-        }
-    }
+    Ok((recorder, replay))
 }
 
-#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
+const MAX_FRAME_TIME: f32 = 0.25; // longest stretch of real time one frame catches up on
 
-struct Player {
-    collision: Rectangle,
-    animations: HashMap<AnimationType, SpriteAnimation>,
-    pos: Vector2,
-    current_animation: AnimationType,
-    direction: Direction,
-    is_attacking: bool,
-    speed: f32,
-}
-
-impl Player {
-    fn new(x: f32, y: f32, width: f32, height: f32, speed: f32) -> Player {
-        let animations = HashMap::new();
-        Player { 
-            collision: Rectangle::new(x, y, width, height), 
-            animations, 
-            pos: Vector2::zero(), 
-            current_animation: AnimationType::Idle(Direction::Down),
-            direction: Direction::Down,
-            is_attacking: false,
-            speed,
-        }
-    }
-
-    fn add_animation(&mut self, 
-        rl: &mut RaylibHandle, thread: &RaylibThread, 
-        animation_type: AnimationType, 
-        file: &str,
-        num_frames: u32, speed: u32)
-    {
-        let sprite = rl.load_texture(&thread, file).unwrap();
-        let animation = SpriteAnimation::new(sprite, num_frames, speed);
-        self.animations.insert(animation_type, animation);
-    }
-
-    fn change_animation(&mut self, animation_type: AnimationType) {
-        if !self.is_attacking {
-            self.current_animation = animation_type;
-        }
-    }
-
-    fn animate(&mut self) {
-        let animation = self.animations.get_mut(&self.current_animation)
-            .expect("Couldn't found animation {:?}, on player.");
-        animation.animate();
-
-        if matches!(self.current_animation, AnimationType::Attack1(_)) {
-            if animation.current_frame == animation.num_frames - 1 {
-                animation.reset();
-                self.is_attacking = false;
-                self.current_animation = AnimationType::Idle(self.current_animation.direction());
-            }
-        }
+fn main() {
+    let (mut recorder, mut replay) = match parse_args() {
+        Ok(modes) => modes,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    let deterministic = recorder.is_some() || replay.is_some();
+
+    let config = Config::load();
+    let mut builder = raylib::init();
+    builder.size(config.width, config.height).title(&config.title);
+    if config.fullscreen {
+        builder.fullscreen();
     }
-
-    fn draw(&self, d: &mut RaylibDrawHandle) {
-        let animation = self.animations.get(&self.current_animation)
-            .expect("Couldn't found animation {:?}, on player.");
-        animation.draw(self.pos, d);
+    if config.vsync {
+        builder.vsync();
     }
-
-    fn move_player(&mut self, dir: Direction) {
-        if !self.is_attacking {
-            match dir {
-                Direction::Up => {
-                    self.pos.y -= self.speed;
-                },
-                Direction::Down => {
-                    self.pos.y += self.speed;
-                },
-                Direction::Right => {
-                    self.pos.x += self.speed;
-                },
-                Direction::Left => {
-                    self.pos.x -= self.speed;
+    let (mut rl, thread) = builder.build();
+    let mut window_mode = WindowMode::new(config.width, config.height);
+    rl.set_window_state(WindowState::default().set_window_resizable(true));
+    rl.set_window_min_size(VIRTUAL_WIDTH, VIRTUAL_HEIGHT);
+
+    // Everything is drawn here at the virtual size, then scaled to the window
+    let mut target = match rl.load_render_texture(&thread, VIRTUAL_WIDTH as u32, VIRTUAL_HEIGHT as u32) {
+        Ok(target) => target,
+        Err(e) => {
+            eprintln!("Couldn't create the render target: {}", e);
+            return;
+        }
+    };
+
+
+    // Declared before the game so it outlives every sound loaded from it
+    let audio = match RaylibAudio::init_audio_device() {
+        Ok(audio) => audio,
+        Err(e) => {
+            eprintln!("Couldn't open the audio device: {}", e);
+            return;
+        }
+    };
+
+    let mut game = match Game::new(&mut rl, &thread, &audio, &config, deterministic) {
+        Ok(game) => game,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+
+    rl.set_target_fps(config.target_fps);
+    // Escape pauses instead of quitting, closing the window still works
+    rl.set_exit_key(None);
+
+    // Updates run in fixed steps whatever the frame rate: real time builds
+    // up in the accumulator and is spent one FIXED_DT at a time, and the
+    // leftover fraction of a step goes to render for interpolation
+    let mut accumulator = 0.0;
+    let mut pending: Vec<InputState> = Vec::new(); // one per player
+    'running: while !rl.window_should_close() && !game.should_quit() {
+        // A long stall (dragging the window, a breakpoint) would otherwise
+        // queue up more steps than can run before the next frame is due
+        accumulator += rl.get_frame_time().min(MAX_FRAME_TIME);
+        let polled = game.poll_input(&rl);
+        if polled.iter().any(|input| input.fullscreen_pressed) {
+            window_mode.toggle_fullscreen(&mut rl);
+        }
+        pending.resize_with(polled.len(), InputState::default);
+        for (pending, polled) in pending.iter_mut().zip(&polled) {
+            pending.merge(polled);
+        }
+
+        while accumulator >= FIXED_DT {
+            // Replays are logged per step, so they play back the same steps
+            let (dt, inputs) = match &mut replay {
+                Some(replay) => match replay.next_frame() {
+                    Some(frame) => frame,
+                    None => break 'running,
                 },
+                None => (FIXED_DT, pending.clone()),
+            };
+            for input in &mut pending {
+                input.clear_pressed();
+            }
+            if let Some(recorder) = &mut recorder
+                && let Err(e) = recorder.record(dt, &inputs)
+            {
+                eprintln!("{}", e);
+                return;
             }
-            self.change_animation(AnimationType::Run(dir));
-        }
-        self.direction = dir;
-    }
-
-    fn play_attack_animation(&mut self) {
-        self.change_animation(AnimationType::Attack1(self.direction));
-        self.is_attacking = true;
-    }
-}
-
-fn main() {
-    let w = 640;
-    let h = 480;
-
-    let (mut rl, thread) = raylib::init()
-        .size(w, h)
-        .title("Non-Hot Reloaded Game")
-        .build();
-
-
-    let mut player = Player::new(42.0, 58.0, 12.0, 28.0, 3.0);
-    player.add_animation(
-        &mut rl, &thread, 
-        AnimationType::Idle(Direction::Down), "resources/Hero/Sprites/IDLE/idle_down.png",
-        8, 20
-    );
-
-    player.add_animation(
-        &mut rl, &thread, 
-        AnimationType::Idle(Direction::Up), "resources/Hero/Sprites/IDLE/idle_up.png",
-        8, 20
-    );
-
-    player.add_animation(
-        &mut rl, &thread, 
-        AnimationType::Idle(Direction::Right), "resources/Hero/Sprites/IDLE/idle_right.png",
-        8, 20
-    );
-
-    player.add_animation(
-        &mut rl, &thread, 
-        AnimationType::Idle(Direction::Left), "resources/Hero/Sprites/IDLE/idle_left.png",
-        8, 20
-    );
-
-    player.add_animation(
-        &mut rl, &thread, 
-        AnimationType::Run(Direction::Down), "resources/Hero/Sprites/RUN/run_down.png",
-        8, 20
-    );
-
-    player.add_animation(
-        &mut rl, &thread, 
-        AnimationType::Run(Direction::Up), "resources/Hero/Sprites/RUN/run_up.png",
-        8, 20
-    );
-
-    player.add_animation(
-        &mut rl, &thread, 
-        AnimationType::Run(Direction::Right), "resources/Hero/Sprites/RUN/run_right.png",
-        8, 20
-    );
-
-    player.add_animation(
-        &mut rl, &thread, 
-        AnimationType::Run(Direction::Left), "resources/Hero/Sprites/RUN/run_left.png",
-        8, 20
-    );
-
-    player.add_animation(
-        &mut rl, &thread, 
-        AnimationType::Attack1(Direction::Down), "resources/Hero/Sprites/ATTACK 1/attack1_down.png",
-        8, 20
-    );
-
-    player.add_animation(
-        &mut rl, &thread, 
-        AnimationType::Attack1(Direction::Up), "resources/Hero/Sprites/ATTACK 1/attack1_up.png",
-        8, 20
-    );
-
-    player.add_animation(
-        &mut rl, &thread, 
-        AnimationType::Attack1(Direction::Left), "resources/Hero/Sprites/ATTACK 1/attack1_left.png",
-        8, 20
-    );
-
-    player.add_animation(
-        &mut rl, &thread, 
-        AnimationType::Attack1(Direction::Right), "resources/Hero/Sprites/ATTACK 1/attack1_right.png",
-        8, 20
-    );
-
-    rl.set_target_fps(60);
-
-    while !rl.window_should_close() {
-        if rl.is_key_down(KeyboardKey::KEY_A) {
-            player.move_player(Direction::Left);
-        }
-        if rl.is_key_down(KeyboardKey::KEY_D) {
-            player.move_player(Direction::Right);
-        }
-        if rl.is_key_down(KeyboardKey::KEY_S) {
-            player.move_player(Direction::Down); 
-        }
-        if rl.is_key_down(KeyboardKey::KEY_W) {
-            player.move_player(Direction::Up);
-        }
 
-        if rl.is_key_released(KeyboardKey::KEY_A) {
-            player.change_animation(AnimationType::Idle(Direction::Left));
-        }
-        if rl.is_key_released(KeyboardKey::KEY_D) {
-            player.change_animation(AnimationType::Idle(Direction::Right));
-        }
-        if rl.is_key_released(KeyboardKey::KEY_S) {
-            player.change_animation(AnimationType::Idle(Direction::Down));
-        }
-        if rl.is_key_released(KeyboardKey::KEY_W) {
-            player.change_animation(AnimationType::Idle(Direction::Up));
-        }
-        if rl.is_key_pressed(KeyboardKey::KEY_SPACE) {
-            player.play_attack_animation();
+            if let Err(e) = game.update(&mut rl, &thread, &inputs, dt) {
+                eprintln!("{}", e);
+                return;
+            }
+            accumulator -= FIXED_DT;
         }
 
+        let letterbox = Letterbox::fit(config.scaling, rl.get_screen_width(), rl.get_screen_height());
+        letterbox.map_mouse(&mut rl);
 
-        player.animate();
-
+        let alpha = accumulator / FIXED_DT;
         let mut d = rl.begin_drawing(&thread);
+        // Texture modes can't nest, so split views are drawn before the target
+        game.render_views(&mut d, &thread, alpha);
+        {
+            let mut d = d.begin_texture_mode(&thread, &mut target);
+            game.render(&mut d, alpha);
+        }
+        d.clear_background(Color::BLACK);
+        // Render textures are stored upside down, the negative height flips it back
+        let source = Rectangle::new(0.0, 0.0, VIRTUAL_WIDTH as f32, -VIRTUAL_HEIGHT as f32);
+        d.draw_texture_pro(&target, source, letterbox.dest(), Vector2::zero(), 0.0, Color::WHITE);
+    }
 
-        d.clear_background(Color::get_color(0x181818FF));
-
-        player.draw(&mut d);
+    if !deterministic && let Err(e) = game.save() {
+        eprintln!("{}", e);
     }
 }
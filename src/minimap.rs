@@ -0,0 +1,161 @@
+use raylib::prelude::*;
+
+use crate::screen::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+use crate::tilemap::{Tile, TileMap};
+
+const MARGIN: f32 = 10.0; // virtual screen pixels from the corner it's in
+const SCALE: f32 = 0.08; // minimap pixels per world pixel
+const DOT_RADIUS: f32 = 2.0;
+const BACKGROUND: Color = Color { r: 0, g: 0, b: 0, a: 160 };
+const OPEN_COLOR: Color = Color { r: 90, g: 90, b: 90, a: 255 };
+const SOLID_COLOR: Color = Color { r: 170, g: 170, b: 170, a: 255 };
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ScreenCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl ScreenCorner {
+    pub fn from_name(name: &str) -> Option<ScreenCorner> {
+        match name {
+            "top_left" => Some(Self::TopLeft),
+            "top_right" => Some(Self::TopRight),
+            "bottom_left" => Some(Self::BottomLeft),
+            "bottom_right" => Some(Self::BottomRight),
+            _ => None,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+enum Cell {
+    Nothing, // no tile in any layer
+    Open,
+    Solid,
+}
+
+// A scaled down view of the map around the camera, showing only the cells
+// some camera has seen and a dot for each player and enemy. Screen space.
+pub struct Minimap {
+    pub rect: Rectangle, // where it's drawn, in virtual screen pixels
+    pub visible: bool,
+    tile_size: f32,
+    columns: usize,
+    cells: Vec<Cell>, // row by row, worked out once when the map loads
+    explored: Vec<bool>, // the same, set as cameras see each cell
+}
+
+impl Minimap {
+    // A square size pixels across, tucked into corner
+    pub fn new(tilemap: &TileMap, size: f32, corner: ScreenCorner, visible: bool) -> Minimap {
+        let (right, bottom) = (VIRTUAL_WIDTH as f32 - size - MARGIN, VIRTUAL_HEIGHT as f32 - size - MARGIN);
+        let (x, y) = match corner {
+            ScreenCorner::TopLeft => (MARGIN, MARGIN),
+            ScreenCorner::TopRight => (right, MARGIN),
+            ScreenCorner::BottomLeft => (MARGIN, bottom),
+            ScreenCorner::BottomRight => (right, bottom),
+        };
+        let mut minimap = Minimap {
+            rect: Rectangle::new(x, y, size, size),
+            visible,
+            tile_size: tilemap.tile_size,
+            columns: 0,
+            cells: Vec::new(),
+            explored: Vec::new(),
+        };
+        minimap.set_map(tilemap);
+        minimap
+    }
+
+    // Forgets what was explored, for a newly loaded map
+    pub fn set_map(&mut self, tilemap: &TileMap) {
+        let bounds = tilemap.bounds();
+        self.tile_size = tilemap.tile_size;
+        self.columns = (bounds.width / tilemap.tile_size) as usize;
+        let rows = (bounds.height / tilemap.tile_size) as usize;
+        self.cells = (0..rows).flat_map(|y| (0..self.columns).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let tiled = tilemap.layers.iter()
+                    .any(|layer| layer.get(y).and_then(|row| row.get(x)).is_some_and(|tile| *tile != Tile::Empty));
+                if tilemap.is_solid_cell(x as i64, y as i64) {
+                    Cell::Solid
+                } else if tiled {
+                    Cell::Open
+                } else {
+                    Cell::Nothing
+                }
+            })
+            .collect();
+        self.explored = vec![false; self.cells.len()];
+    }
+
+    // Cells overlapping view, a world space rectangle, clamped to the map
+    fn cells_in(&self, view: Rectangle) -> impl Iterator<Item = (usize, usize)> + use<> {
+        let rows = self.cells.len() / self.columns.max(1);
+        let cell = |v: f32, count: usize| ((v / self.tile_size).floor().max(0.0) as usize).min(count);
+        let (left, right) = (cell(view.x, self.columns), cell(view.x + view.width + self.tile_size, self.columns));
+        let (top, bottom) = (cell(view.y, rows), cell(view.y + view.height + self.tile_size, rows));
+        (top..bottom).flat_map(move |y| (left..right).map(move |x| (x, y)))
+    }
+
+    pub fn reveal(&mut self, view: Rectangle) {
+        for (x, y) in self.cells_in(view) {
+            self.explored[y * self.columns + x] = true;
+        }
+    }
+
+    // The world area shown, centered on focus but kept inside the map. A map
+    // smaller than that sits in the top left.
+    fn region(&self, focus: Vector2) -> Rectangle {
+        let (width, height) = (self.rect.width / SCALE, self.rect.height / SCALE);
+        let map_width = self.columns as f32 * self.tile_size;
+        let map_height = (self.cells.len() / self.columns.max(1)) as f32 * self.tile_size;
+        let x = (focus.x - width / 2.0).min(map_width - width).max(0.0);
+        let y = (focus.y - height / 2.0).min(map_height - height).max(0.0);
+        Rectangle::new(x, y, width, height)
+    }
+
+    // Dots are in world space, those outside the shown area are left out
+    pub fn draw(&self, d: &mut RaylibDrawHandle, focus: Vector2, players: &[Vector2], enemies: &[Vector2]) {
+        if !self.visible {
+            return;
+        }
+        let region = self.region(focus);
+        let to_screen = |pos: Vector2| Vector2::new(
+            self.rect.x + (pos.x - region.x) * SCALE,
+            self.rect.y + (pos.y - region.y) * SCALE,
+        );
+
+        d.draw_rectangle_rec(self.rect, BACKGROUND);
+        let size = (self.tile_size * SCALE).max(1.0);
+        for (x, y) in self.cells_in(region) {
+            let i = y * self.columns + x;
+            if !self.explored[i] {
+                continue;
+            }
+            let color = match self.cells[i] {
+                Cell::Nothing => continue,
+                Cell::Open => OPEN_COLOR,
+                Cell::Solid => SOLID_COLOR,
+            };
+            let corner = to_screen(Vector2::new(x as f32 * self.tile_size, y as f32 * self.tile_size));
+            // Trimmed where a cell hangs over the minimap's edge
+            let cell = Rectangle::new(corner.x, corner.y, size, size);
+            if let Some(clipped) = self.rect.get_collision_rec(&cell) {
+                d.draw_rectangle_rec(clipped, color);
+            }
+        }
+
+        let dots = enemies.iter().map(|&pos| (pos, Color::RED))
+            .chain(players.iter().map(|&pos| (pos, Color::WHITE)));
+        for (pos, color) in dots {
+            if region.check_collision_point_rec(pos) {
+                d.draw_circle_v(to_screen(pos), DOT_RADIUS, color);
+            }
+        }
+        d.draw_rectangle_lines_ex(self.rect, 1.0, Color::WHITE);
+    }
+}
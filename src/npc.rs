@@ -0,0 +1,59 @@
+use raylib::prelude::*;
+
+use crate::animation::{AnimatedSprite, AnimationType, direction_from_vector};
+use crate::drawable::Drawable;
+use crate::object::GameObject;
+use crate::world::World;
+
+// A character that plays its current animation in place and has something
+// to say when the player talks to it
+pub struct Npc {
+    pub pos: Vector2,
+    pub sprite: AnimatedSprite,
+    pub dialogue: String,
+}
+
+impl Npc {
+    pub fn new(pos: Vector2, sprite: AnimatedSprite, dialogue: &str) -> Npc {
+        Npc { pos, sprite, dialogue: dialogue.to_string() }
+    }
+
+    pub fn center(&self) -> Vector2 {
+        let bounds = self.bounds();
+        Vector2::new(bounds.x + bounds.width / 2.0, bounds.y + bounds.height / 2.0)
+    }
+
+    pub fn is_in_range(&self, player_pos: Vector2, radius: f32) -> bool {
+        self.center().distance_to(player_pos) <= radius
+    }
+
+    pub fn face(&mut self, target: Vector2) {
+        let facing = direction_from_vector(target - self.center(), self.sprite.current().direction());
+        self.sprite.change(AnimationType::Idle(facing));
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, view: Rectangle) {
+        self.sprite.draw(self.pos, view, d);
+    }
+}
+
+impl Drawable for Npc {
+    fn depth(&self) -> f32 {
+        let bounds = self.sprite.bounds(self.pos);
+        bounds.y + bounds.height
+    }
+
+    fn render(&self, d: &mut RaylibDrawHandle, view: Rectangle, _alpha: f32) {
+        self.draw(d, view);
+    }
+}
+
+impl GameObject for Npc {
+    fn update(&mut self, dt: f32, _world: &World) {
+        self.sprite.animate(dt);
+    }
+
+    fn bounds(&self) -> Rectangle {
+        self.sprite.bounds(self.pos)
+    }
+}
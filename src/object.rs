@@ -0,0 +1,12 @@
+use raylib::prelude::*;
+
+use crate::drawable::Drawable;
+use crate::world::World;
+
+// What every entity in the world has in common, so the game can step and
+// draw them all alike. Drawing comes from Drawable, which also orders it.
+pub trait GameObject: Drawable {
+    fn update(&mut self, dt: f32, world: &World);
+    // Where it collides, in world space
+    fn bounds(&self) -> Rectangle;
+}
@@ -0,0 +1,114 @@
+use crate::json::Value;
+
+// What an objective asks for. Plain data rather than a callback, so the
+// list can go in the save file.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ObjectiveKind {
+    CollectCoins(u32), // held between all the players
+    DefeatEnemies(u32),
+    ReachCheckpoint,
+}
+
+// What the objectives are checked against, gathered by the game each update
+pub struct Progress {
+    pub coins: u32,
+    pub kills: u32,
+    pub checkpoint_reached: bool,
+}
+
+// Once complete it stays that way, spending the coins doesn't undo it
+#[derive(PartialEq, Debug, Clone)]
+pub struct Objective {
+    pub kind: ObjectiveKind,
+    pub complete: bool,
+}
+
+impl Objective {
+    pub fn new(kind: ObjectiveKind) -> Objective {
+        Objective { kind, complete: false }
+    }
+
+    pub fn description(&self, progress: &Progress) -> String {
+        match self.kind {
+            ObjectiveKind::CollectCoins(count) => format!("Collect {} coins ({}/{})", count, progress.coins.min(count), count),
+            ObjectiveKind::DefeatEnemies(count) => format!("Defeat {} enemies ({}/{})", count, progress.kills.min(count), count),
+            ObjectiveKind::ReachCheckpoint => "Reach a checkpoint".to_string(),
+        }
+    }
+
+    // True on the update it's completed
+    pub fn update(&mut self, progress: &Progress) -> bool {
+        if self.complete {
+            return false;
+        }
+        self.complete = match self.kind {
+            ObjectiveKind::CollectCoins(count) => progress.coins >= count,
+            ObjectiveKind::DefeatEnemies(count) => progress.kills >= count,
+            ObjectiveKind::ReachCheckpoint => progress.checkpoint_reached,
+        };
+        self.complete
+    }
+
+    // { kind, count, complete }, count only for the kinds that have one
+    pub fn to_value(&self) -> Value {
+        let (kind, count) = match self.kind {
+            ObjectiveKind::CollectCoins(count) => ("collect_coins", Some(count)),
+            ObjectiveKind::DefeatEnemies(count) => ("defeat_enemies", Some(count)),
+            ObjectiveKind::ReachCheckpoint => ("reach_checkpoint", None),
+        };
+        let mut fields = vec![("kind".to_string(), Value::String(kind.to_string()))];
+        if let Some(count) = count {
+            fields.push(("count".to_string(), Value::Number(count as f64)));
+        }
+        fields.push(("complete".to_string(), Value::Bool(self.complete)));
+        Value::Object(fields)
+    }
+
+    pub fn from_value(value: &Value) -> Result<Objective, String> {
+        let count = || value.get("count").and_then(Value::as_f64)
+            .filter(|&count| count >= 0.0)
+            .map(|count| count as u32)
+            .ok_or("Objective is missing a valid \"count\"");
+        let kind = match value.get("kind").and_then(Value::as_str) {
+            Some("collect_coins") => ObjectiveKind::CollectCoins(count()?),
+            Some("defeat_enemies") => ObjectiveKind::DefeatEnemies(count()?),
+            Some("reach_checkpoint") => ObjectiveKind::ReachCheckpoint,
+            Some(other) => return Err(format!("Unknown objective kind \"{}\"", other)),
+            None => return Err("Objective is missing \"kind\"".to_string()),
+        };
+        let complete = value.get("complete").and_then(Value::as_bool).unwrap_or(false);
+        Ok(Objective { kind, complete })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coins(coins: u32) -> Progress {
+        Progress { coins, kills: 0, checkpoint_reached: false }
+    }
+
+    #[test]
+    fn collecting_the_coins_completes_it_once() {
+        let mut objective = Objective::new(ObjectiveKind::CollectCoins(5));
+        assert!(!objective.update(&coins(4)));
+        assert!(!objective.complete);
+        assert_eq!(objective.description(&coins(4)), "Collect 5 coins (4/5)");
+
+        assert!(objective.update(&coins(5)));
+        assert!(objective.complete);
+        assert!(!objective.update(&coins(6)));
+
+        // Spending them again doesn't undo it
+        objective.update(&coins(0));
+        assert!(objective.complete);
+    }
+
+    #[test]
+    fn round_trips_through_a_value() {
+        let mut objective = Objective::new(ObjectiveKind::DefeatEnemies(3));
+        objective.complete = true;
+        assert_eq!(Objective::from_value(&objective.to_value()), Ok(objective));
+    }
+}
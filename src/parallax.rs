@@ -0,0 +1,51 @@
+use std::rc::Rc;
+
+use raylib::prelude::*;
+
+use crate::screen::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
+pub struct ParallaxLayer {
+    pub texture: Rc<Texture2D>,
+    // How far the layer moves for each pixel the camera does: 0 stays put
+    // like a sky, 1 scrolls with the world
+    pub scroll_factor: f32,
+}
+
+// Textures repeated to fill the screen behind everything, furthest first
+pub struct ParallaxBackground {
+    pub layers: Vec<ParallaxLayer>,
+}
+
+impl ParallaxBackground {
+    pub fn new() -> ParallaxBackground {
+        ParallaxBackground { layers: Vec::new() }
+    }
+
+    pub fn add_layer(&mut self, texture: Rc<Texture2D>, scroll_factor: f32) {
+        self.layers.push(ParallaxLayer { texture, scroll_factor });
+    }
+
+    // Screen space, call before begin_mode2D. Zoom is ignored, a layer only
+    // lines up exactly with the world at a zoom of 1.
+    pub fn draw(&self, d: &mut RaylibDrawHandle, camera: &Camera2D) {
+        for layer in &self.layers {
+            let width = layer.texture.width as f32;
+            let height = layer.texture.height as f32;
+            if width <= 0.0 || height <= 0.0 {
+                continue;
+            }
+
+            let scroll = camera.target * layer.scroll_factor - camera.offset;
+            let start = Vector2::new(-scroll.x.rem_euclid(width), -scroll.y.rem_euclid(height));
+            let mut y = start.y;
+            while y < VIRTUAL_HEIGHT as f32 {
+                let mut x = start.x;
+                while x < VIRTUAL_WIDTH as f32 {
+                    d.draw_texture_v(&*layer.texture, Vector2::new(x, y), Color::WHITE);
+                    x += width;
+                }
+                y += height;
+            }
+        }
+    }
+}
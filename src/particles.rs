@@ -0,0 +1,93 @@
+use std::f32::consts::TAU;
+
+use raylib::prelude::*;
+
+use crate::pool::{Pool, Poolable};
+use crate::rng::Rng;
+
+const MAX_PARTICLES: usize = 256; // bursts past this are cut short
+const DUST_COLOR: Color = Color { r: 200, g: 190, b: 170, a: 255 };
+const DRAG: f32 = 4.0; // fraction of velocity lost per second, roughly
+
+pub struct Particle {
+    pub pos: Vector2,
+    pub velocity: Vector2, // pixels per second
+    pub lifetime: f32, // seconds from spawning to disappearing
+    pub age: f32,
+    pub color: Color,
+    pub size: f32,
+}
+
+impl Poolable for Particle {
+    // spawn_burst sets everything else
+    fn reset(&mut self) {
+        self.age = 0.0;
+    }
+
+    fn is_expired(&self) -> bool {
+        self.age >= self.lifetime
+    }
+}
+
+// A fixed-capacity pool of short-lived dust specks, bursts reuse the slots
+// of the ones that have faded out
+pub struct ParticleSystem {
+    particles: Pool<Particle>,
+}
+
+impl ParticleSystem {
+    pub fn new() -> ParticleSystem {
+        ParticleSystem {
+            particles: Pool::new(MAX_PARTICLES, || Particle {
+                pos: Vector2::zero(),
+                velocity: Vector2::zero(),
+                lifetime: 0.0,
+                age: 0.0,
+                color: DUST_COLOR,
+                size: 0.0,
+            }),
+        }
+    }
+
+    // Particles flying out every which way from pos
+    pub fn spawn_burst(&mut self, pos: Vector2, count: usize, rng: &mut Rng) {
+        for _ in 0..count {
+            let Some(particle) = self.particles.acquire() else {
+                break;
+            };
+            let angle = rng.range_f32(0.0, TAU);
+            let speed = rng.range_f32(40.0, 120.0);
+            particle.pos = pos;
+            particle.velocity = Vector2::new(angle.cos(), angle.sin()) * speed;
+            particle.lifetime = rng.range_f32(0.3, 0.6);
+            particle.color = DUST_COLOR;
+            particle.size = rng.range_f32(2.0, 4.0);
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let slowdown = (1.0 - DRAG * dt).max(0.0);
+        for particle in self.particles.iter_mut() {
+            particle.age += dt;
+            particle.pos += particle.velocity * dt;
+            particle.velocity *= slowdown;
+        }
+        self.particles.release_expired();
+    }
+
+    // World space, fading out over each particle's lifetime
+    pub fn draw(&self, d: &mut RaylibDrawHandle, view: Rectangle) {
+        for particle in self.particles.iter() {
+            let rect = Rectangle::new(
+                particle.pos.x - particle.size / 2.0,
+                particle.pos.y - particle.size / 2.0,
+                particle.size,
+                particle.size,
+            );
+            if rect.check_collision_recs(&view) {
+                let fade = 1.0 - particle.age / particle.lifetime;
+                d.draw_rectangle_rec(rect, particle.color.alpha(fade));
+            }
+        }
+    }
+}
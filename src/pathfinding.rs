@@ -0,0 +1,177 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use raylib::prelude::*;
+
+// Step costs in tenths of a tile, so a diagonal is close to sqrt(2) while
+// everything stays in integers and ties break the same way every run
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+// Which cells of a tile grid anything can stand in, for A* searches. Built
+// from the same obstacles as collision, so Tiled collision objects count.
+pub struct NavGrid {
+    pub tile_size: f32,
+    origin: Vector2, // world position of cell (0, 0)
+    columns: usize,
+    rows: usize,
+    blocked: Vec<bool>, // row by row
+    pub diagonal: bool, // 8-connected instead of 4
+}
+
+impl NavGrid {
+    pub fn new(bounds: Rectangle, tile_size: f32, obstacles: &[Rectangle], diagonal: bool) -> NavGrid {
+        let columns = (bounds.width / tile_size).ceil().max(0.0) as usize;
+        let rows = (bounds.height / tile_size).ceil().max(0.0) as usize;
+        let mut blocked = vec![false; columns * rows];
+        for y in 0..rows {
+            for x in 0..columns {
+                // Shrunk a little so an obstacle only touching the cell's edge doesn't block it
+                let cell = Rectangle::new(
+                    bounds.x + x as f32 * tile_size + 1.0,
+                    bounds.y + y as f32 * tile_size + 1.0,
+                    tile_size - 2.0,
+                    tile_size - 2.0,
+                );
+                blocked[y * columns + x] = obstacles.iter().any(|obstacle| obstacle.check_collision_recs(&cell));
+            }
+        }
+        NavGrid { tile_size, origin: Vector2::new(bounds.x, bounds.y), columns, rows, blocked, diagonal }
+    }
+
+    // The cell a world position falls in, None outside the grid
+    pub fn tile_at(&self, pos: Vector2) -> Option<(usize, usize)> {
+        let x = ((pos.x - self.origin.x) / self.tile_size).floor();
+        let y = ((pos.y - self.origin.y) / self.tile_size).floor();
+        if x < 0.0 || y < 0.0 || x as usize >= self.columns || y as usize >= self.rows {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+
+    pub fn tile_center(&self, (x, y): (usize, usize)) -> Vector2 {
+        Vector2::new(
+            self.origin.x + (x as f32 + 0.5) * self.tile_size,
+            self.origin.y + (y as f32 + 0.5) * self.tile_size,
+        )
+    }
+
+    pub fn is_blocked(&self, (x, y): (usize, usize)) -> bool {
+        x >= self.columns || y >= self.rows || self.blocked[y * self.columns + x]
+    }
+
+    // Diagonal steps don't cut past the corner of a blocked cell, or
+    // something as wide as a tile would catch on it
+    fn neighbors(&self, (x, y): (usize, usize)) -> impl Iterator<Item = ((usize, usize), u32)> + '_ {
+        const STEPS: [(i32, i32); 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+        let count = if self.diagonal { 8 } else { 4 };
+        STEPS[..count].iter().filter_map(move |&(dx, dy)| {
+            let nx = x.checked_add_signed(dx as isize)?;
+            let ny = y.checked_add_signed(dy as isize)?;
+            if self.is_blocked((nx, ny)) {
+                return None;
+            }
+            if dx != 0 && dy != 0 {
+                if self.is_blocked((nx, y)) || self.is_blocked((x, ny)) {
+                    return None;
+                }
+                return Some(((nx, ny), DIAGONAL_COST));
+            }
+            Some(((nx, ny), STRAIGHT_COST))
+        })
+    }
+
+    // Never overestimates, so A* still finds the shortest path
+    fn heuristic(&self, (ax, ay): (usize, usize), (bx, by): (usize, usize)) -> u32 {
+        let dx = ax.abs_diff(bx) as u32;
+        let dy = ay.abs_diff(by) as u32;
+        if self.diagonal {
+            STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+        } else {
+            STRAIGHT_COST * (dx + dy)
+        }
+    }
+
+    // The centers of the tiles to walk through from the one containing from
+    // to the one containing to, not including the start. None when either
+    // end is off the grid, the goal is blocked or nothing connects them. The
+    // start may be blocked, something pressed into a wall can still leave.
+    pub fn find_path(&self, from: Vector2, to: Vector2) -> Option<Vec<Vector2>> {
+        let start = self.tile_at(from)?;
+        let goal = self.tile_at(to)?;
+        if self.is_blocked(goal) {
+            return None;
+        }
+
+        let index = |(x, y): (usize, usize)| y * self.columns + x;
+        let mut cost = vec![u32::MAX; self.blocked.len()];
+        let mut came_from: Vec<Option<(usize, usize)>> = vec![None; self.blocked.len()];
+        let mut open = BinaryHeap::new();
+        cost[index(start)] = 0;
+        open.push(Reverse((self.heuristic(start, goal), 0, start)));
+
+        while let Some(Reverse((_, so_far, tile))) = open.pop() {
+            if tile == goal {
+                let mut path = Vec::new();
+                let mut current = goal;
+                while current != start {
+                    path.push(self.tile_center(current));
+                    current = came_from[index(current)]?;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            if so_far > cost[index(tile)] {
+                continue; // a cheaper way here was already expanded
+            }
+
+            for (next, step) in self.neighbors(tile) {
+                let next_cost = so_far + step;
+                if next_cost < cost[index(next)] {
+                    cost[index(next)] = next_cost;
+                    came_from[index(next)] = Some(tile);
+                    open.push(Reverse((next_cost + self.heuristic(next, goal), next_cost, next)));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ten by ten tiles of 32 pixels with a wall down column 5, open only in
+    // the bottom row
+    fn walled_grid(diagonal: bool) -> NavGrid {
+        let wall = Rectangle::new(160.0, 0.0, 32.0, 288.0);
+        NavGrid::new(Rectangle::new(0.0, 0.0, 320.0, 320.0), 32.0, &[wall], diagonal)
+    }
+
+    #[test]
+    fn path_goes_around_a_wall() {
+        let grid = walled_grid(false);
+        let from = grid.tile_center((2, 2));
+        let to = grid.tile_center((8, 2));
+        let path = grid.find_path(from, to).unwrap();
+
+        assert_eq!(path.last(), Some(&to));
+        assert!(path.iter().all(|&pos| !grid.is_blocked(grid.tile_at(pos).unwrap())));
+        assert!(path.contains(&grid.tile_center((5, 9))));
+        // Down 7, across 6 and back up 7
+        assert_eq!(path.len(), 20);
+    }
+
+    #[test]
+    fn diagonal_paths_are_shorter() {
+        let path = walled_grid(true).find_path(Vector2::new(80.0, 80.0), Vector2::new(272.0, 80.0)).unwrap();
+        assert!(path.len() < 20);
+    }
+
+    #[test]
+    fn no_path_into_a_wall() {
+        let grid = walled_grid(false);
+        assert!(grid.find_path(grid.tile_center((2, 2)), grid.tile_center((5, 2))).is_none());
+    }
+}
@@ -0,0 +1,142 @@
+use raylib::prelude::*;
+
+// What a body is, one bit each so a mask can hold several
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct CollisionLayer(u8);
+
+impl CollisionLayer {
+    pub const WALL: CollisionLayer = CollisionLayer(1 << 0);
+    pub const PLAYER: CollisionLayer = CollisionLayer(1 << 1);
+    pub const ENEMY: CollisionLayer = CollisionLayer(1 << 2);
+    pub const PLAYER_SHOT: CollisionLayer = CollisionLayer(1 << 3);
+    pub const ALL: CollisionLayer = CollisionLayer(u8::MAX);
+
+    // Const so masks can be built in constants
+    pub const fn union(self, other: CollisionLayer) -> CollisionLayer {
+        CollisionLayer(self.0 | other.0)
+    }
+
+    pub fn intersects(self, other: CollisionLayer) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+// The layer a body is on and the layers it runs into or hits
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct CollisionFilter {
+    pub layer: CollisionLayer,
+    pub mask: CollisionLayer,
+}
+
+// The map's walls stop anything that lets them
+pub const WALLS: CollisionFilter = CollisionFilter { layer: CollisionLayer::WALL, mask: CollisionLayer::ALL };
+
+// Both have to want it, so either side can opt out of a pairing
+pub fn can_collide(a: CollisionFilter, b: CollisionFilter) -> bool {
+    a.mask.intersects(b.layer) && b.mask.intersects(a.layer)
+}
+
+// Moves bounds by delta one axis at a time, stopping flush against anything
+// it runs into so hitting a wall only cancels the blocked axis and the box
+// slides along it. Returns how far it actually got.
+pub fn move_and_collide(bounds: Rectangle, delta: Vector2, obstacles: &[Rectangle]) -> Vector2 {
+    let mut moved = bounds;
+
+    moved.x += delta.x;
+    for obstacle in obstacles {
+        if moved.check_collision_recs(obstacle) {
+            if delta.x > 0.0 {
+                moved.x = obstacle.x - moved.width;
+            } else if delta.x < 0.0 {
+                moved.x = obstacle.x + obstacle.width;
+            }
+        }
+    }
+
+    moved.y += delta.y;
+    for obstacle in obstacles {
+        if moved.check_collision_recs(obstacle) {
+            if delta.y > 0.0 {
+                moved.y = obstacle.y - moved.height;
+            } else if delta.y < 0.0 {
+                moved.y = obstacle.y + obstacle.height;
+            }
+        }
+    }
+
+    Vector2::new(moved.x - bounds.x, moved.y - bounds.y)
+}
+
+const KNOCKBACK_TIME: f32 = 0.2; // seconds for a push to die down
+
+// Moves from towards to by at most max_step, landing on it once it's that close
+pub fn approach(from: Vector2, to: Vector2, max_step: f32) -> Vector2 {
+    let diff = to - from;
+    let distance = diff.length();
+    if distance <= max_step { to } else { from + diff / distance * max_step }
+}
+
+// A push that starts at full speed and slows to a stop
+#[derive(Default)]
+pub struct Knockback {
+    velocity: Vector2,
+    timer: f32,
+}
+
+impl Knockback {
+    pub fn apply(&mut self, dir: Vector2, strength: f32) {
+        if dir == Vector2::zero() {
+            return;
+        }
+        self.velocity = dir.normalized() * strength;
+        self.timer = KNOCKBACK_TIME;
+    }
+
+    // How far the push moves things this tick
+    pub fn step(&mut self, dt: f32) -> Vector2 {
+        if self.timer <= 0.0 {
+            return Vector2::zero();
+        }
+        let delta = self.velocity * (self.timer / KNOCKBACK_TIME) * dt;
+        self.timer = (self.timer - dt).max(0.0);
+        delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(layer: CollisionLayer, mask: CollisionLayer) -> CollisionFilter {
+        CollisionFilter { layer, mask }
+    }
+
+    #[test]
+    fn one_side_wanting_it_isnt_enough() {
+        let ghost = filter(CollisionLayer::ENEMY, CollisionLayer::WALL);
+        let player = filter(CollisionLayer::PLAYER, CollisionLayer::ENEMY);
+        assert!(!can_collide(ghost, player));
+        assert!(!can_collide(player, ghost));
+
+        let enemy = filter(CollisionLayer::ENEMY, CollisionLayer::PLAYER);
+        assert!(can_collide(enemy, player));
+        assert!(can_collide(player, enemy));
+    }
+
+    #[test]
+    fn masks_that_share_no_layer_never_meet() {
+        let shot = filter(CollisionLayer::PLAYER_SHOT, CollisionLayer::ENEMY);
+        let walker = filter(CollisionLayer::PLAYER, CollisionLayer::WALL);
+        assert!(!can_collide(shot, walker));
+        assert!(!can_collide(shot, filter(CollisionLayer::ENEMY, CollisionLayer(0))));
+        assert!(can_collide(WALLS, filter(CollisionLayer::ENEMY, CollisionLayer::WALL)));
+    }
+
+    #[test]
+    fn a_layer_only_meets_itself_when_its_mask_has_it() {
+        let crowd = filter(CollisionLayer::PLAYER, CollisionLayer::PLAYER);
+        assert!(can_collide(crowd, crowd));
+        let swarm = filter(CollisionLayer::ENEMY, CollisionLayer::PLAYER);
+        assert!(!can_collide(swarm, swarm));
+    }
+}
@@ -0,0 +1,790 @@
+use raylib::prelude::*;
+
+use crate::animation::{AnimatedSprite, AnimationType, Direction, direction_from_vector};
+use crate::animation_controller::{AnimationController, AnimationState};
+use crate::cooldown::{AbilityId, Cooldowns};
+use crate::drawable::Drawable;
+use crate::input::InputState;
+use crate::inventory::Inventory;
+use crate::object::GameObject;
+use crate::physics::{CollisionFilter, CollisionLayer, Knockback, approach, move_and_collide};
+use crate::save::PlayerSnapshot;
+use crate::world::World;
+
+const MAX_HEALTH: i32 = 5;
+const INVULNERABILITY_TIME: f32 = 1.0; // seconds after a hit before the next one lands
+const HIT_FLASH_TIME: f32 = 0.1;
+const ATTACK_REACH: f32 = 16.0; // in unscaled sprite pixels
+const DASH_TIME: f32 = 0.15;
+const DASH_COOLDOWN: f32 = 1.0; // seconds after a dash ends before the next
+const ATTACK_COOLDOWN: f32 = 0.3; // seconds from the start of a swing to the next
+const FIRE_COOLDOWN: f32 = 0.4; // seconds between shots
+const DASH_SPEED_MULTIPLIER: f32 = 3.0;
+const DASH_ANIMATION_SPEEDUP: f32 = 2.0;
+const XP_PER_LEVEL: u32 = 10; // see xp_to_next
+const LEVEL_HEALTH_BONUS: i32 = 1;
+const LEVEL_SPEED_BONUS: f32 = 5.0; // pixels per second
+const MOVING_THRESHOLD: f32 = 10.0; // pixels per second, slower than this shows as standing still
+const BLOCKED_TOLERANCE: f32 = 0.001; // pixels short of a full step that are only rounding, not a wall
+
+pub struct Player {
+    collision: Rectangle,
+    // Collision box relative to the sprite frame, in unscaled sprite pixels
+    collision_offset: Vector2,
+    collision_size: Vector2,
+    pub sprite: AnimatedSprite,
+    pub pos: Vector2,
+    prev_pos: Vector2, // pos as of the previous update, for drawing between updates
+    last_direction: Direction,
+    last_pressed: Option<Direction>, // most recent movement key, for diagonal tie-breaks
+    is_moving: bool, // faster than MOVING_THRESHOLD as of the last handle_input
+    velocity: Vector2, // pixels per second
+    animation: AnimationController, // the sprite only ever plays what this picks
+    hitbox_active: bool, // from the attack's event frame until the swing ends
+    swing: u32, // counts attacks so a target can tell one swing from the next
+    stepped: bool, // a foot touched down during the last animate
+    dash_started: bool, // a dash began during the last handle_input
+    fired: bool, // a shot should be spawned for the last handle_input
+    fire_direction: Vector2, // unit length, which way that shot goes
+    aim: Option<Vector2>, // the world point aim_at set, for the next handle_input
+    speed: f32, // pixels per second
+    acceleration: f32, // pixels per second per second, towards full speed while moving
+    friction: f32, // pixels per second per second, towards a stop once input stops
+    pub health: i32,
+    pub max_health: i32,
+    pub level: u32, // from 1
+    pub xp: u32, // towards the next level, starts over at each one
+    pub inventory: Inventory,
+    invulnerable_timer: f32,
+    pub blink_rate: f32, // times a second the sprite disappears while invulnerable
+    dash_timer: f32,
+    cooldowns: Cooldowns,
+    dash_direction: Vector2,
+    knockback: Knockback,
+    pub filter: CollisionFilter,
+}
+
+// Players bump into walls, each other and enemies but not their own shots
+pub const PLAYER_FILTER: CollisionFilter = CollisionFilter {
+    layer: CollisionLayer::PLAYER,
+    mask: CollisionLayer::WALL.union(CollisionLayer::PLAYER).union(CollisionLayer::ENEMY),
+};
+
+// Sets up a Player by name rather than by argument order. Left alone it
+// moves like the hero, but the collision box and scale belong to whatever
+// sheet it's drawn with, so by default it's unscaled with a 16 pixel box.
+pub struct PlayerBuilder {
+    collision: Rectangle, // relative to the sprite frame, in unscaled sprite pixels
+    speed: f32, // pixels per second
+    acceleration: f32, // pixels per second per second
+    friction: f32, // pixels per second per second
+    scale: f32,
+}
+
+impl PlayerBuilder {
+    pub fn new() -> PlayerBuilder {
+        PlayerBuilder {
+            collision: Rectangle::new(0.0, 0.0, 16.0, 16.0),
+            speed: 180.0,
+            acceleration: 1200.0,
+            friction: 1500.0,
+            scale: 1.0,
+        }
+    }
+
+    pub fn collision(mut self, collision: Rectangle) -> PlayerBuilder {
+        self.collision = collision;
+        self
+    }
+
+    pub fn speed(mut self, speed: f32) -> PlayerBuilder {
+        self.speed = speed;
+        self
+    }
+
+    pub fn acceleration(mut self, acceleration: f32) -> PlayerBuilder {
+        self.acceleration = acceleration;
+        self
+    }
+
+    pub fn friction(mut self, friction: f32) -> PlayerBuilder {
+        self.friction = friction;
+        self
+    }
+
+    pub fn scale(mut self, scale: f32) -> PlayerBuilder {
+        self.scale = scale;
+        self
+    }
+
+    pub fn build(self) -> Player {
+        let PlayerBuilder { collision, speed, acceleration, friction, scale } = self;
+        Player {
+            collision: Rectangle::new(collision.x * scale, collision.y * scale, collision.width * scale, collision.height * scale),
+            collision_offset: Vector2::new(collision.x, collision.y),
+            collision_size: Vector2::new(collision.width, collision.height),
+            sprite: AnimatedSprite::new(scale),
+            pos: Vector2::zero(),
+            prev_pos: Vector2::zero(),
+            last_direction: Direction::Down,
+            last_pressed: None,
+            is_moving: false,
+            velocity: Vector2::zero(),
+            animation: AnimationController::new(Direction::Down),
+            hitbox_active: false,
+            swing: 0,
+            stepped: false,
+            dash_started: false,
+            fired: false,
+            fire_direction: Vector2::new(0.0, 1.0),
+            aim: None,
+            speed,
+            acceleration,
+            friction,
+            health: MAX_HEALTH,
+            max_health: MAX_HEALTH,
+            level: 1,
+            xp: 0,
+            inventory: Inventory::new(),
+            invulnerable_timer: 0.0,
+            blink_rate: 10.0,
+            dash_timer: 0.0,
+            cooldowns: Cooldowns::new(),
+            dash_direction: Vector2::zero(),
+            knockback: Knockback::default(),
+            filter: PLAYER_FILTER,
+        }
+    }
+}
+
+impl Player {
+    // Asks for what the player is doing now, which the controller may turn
+    // down (see AnimationController)
+    pub fn request(&mut self, state: AnimationState) {
+        if self.animation.request(state, self.last_direction) {
+            self.sprite.change(self.animation.animation());
+        }
+    }
+
+    fn force_animation(&mut self, animation_type: AnimationType) {
+        self.animation.force(animation_type);
+        self.sprite.change(animation_type);
+    }
+
+    fn is_attacking(&self) -> bool {
+        self.animation.state() == AnimationState::Attacking
+    }
+
+    // Level n takes 10 * n XP to finish: 10 to reach level 2, 20 more for
+    // level 3 and so on
+    pub fn xp_to_next(&self) -> u32 {
+        XP_PER_LEVEL * self.level
+    }
+
+    // Returns how many levels it gained, enough XP can carry it over several.
+    // Each one raises max health and speed, and leveling heals fully.
+    pub fn add_xp(&mut self, amount: u32) -> u32 {
+        if !self.is_alive() {
+            return 0;
+        }
+
+        self.xp += amount;
+        let mut gained = 0;
+        while self.xp >= self.xp_to_next() {
+            self.xp -= self.xp_to_next();
+            self.level += 1;
+            self.max_health += LEVEL_HEALTH_BONUS;
+            self.speed += LEVEL_SPEED_BONUS;
+            gained += 1;
+        }
+        if gained > 0 {
+            self.health = self.max_health;
+        }
+        gained
+    }
+
+    // Back on its feet after dying, briefly untouchable so it isn't hit again
+    // the moment it appears
+    pub fn revive(&mut self, health: i32) {
+        self.health = health.clamp(1, self.max_health);
+        self.invulnerable_timer = INVULNERABILITY_TIME;
+        self.is_moving = false;
+        self.velocity = Vector2::zero();
+        self.hitbox_active = false;
+        self.dash_timer = 0.0;
+        self.knockback = Knockback::default();
+        self.force_animation(AnimationType::Idle(self.last_direction));
+    }
+
+    // Dead and done falling over. Without a death animation, which the hero's
+    // sheets don't have yet, the sprite just stands idle and there's nothing
+    // to wait for.
+    pub fn death_finished(&self) -> bool {
+        let death = self.animation.animation();
+        !self.is_alive() && (!self.sprite.has_animation(death) || (self.sprite.current() == death && self.sprite.is_finished()))
+    }
+
+    pub fn take_damage(&mut self, amount: i32) {
+        if !self.is_alive() || self.is_invulnerable() {
+            return;
+        }
+
+        self.health = (self.health - amount).max(0);
+        self.invulnerable_timer = INVULNERABILITY_TIME;
+        self.sprite.flash(Color::RED, HIT_FLASH_TIME);
+        if !self.is_alive() {
+            self.is_moving = false;
+            self.velocity = Vector2::zero();
+            self.request(AnimationState::Dead);
+        }
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable_timer > 0.0
+    }
+
+    pub fn stepped(&self) -> bool {
+        self.stepped
+    }
+
+    pub fn dash_started(&self) -> bool {
+        self.dash_started
+    }
+
+    // The player doesn't own the shots, the game spawns one when this is set
+    pub fn fired(&self) -> bool {
+        self.fired
+    }
+
+    pub fn fire_direction(&self) -> Vector2 {
+        self.fire_direction
+    }
+
+    // Faces target on the next handle_input instead of the way the player
+    // moves. The sprite shows the closest direction it has an animation for,
+    // shots still go straight at target.
+    pub fn aim_at(&mut self, target: Vector2) {
+        self.aim = Some(target);
+    }
+
+    pub fn current_animation_type(&self) -> AnimationType {
+        self.sprite.current()
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.health > 0
+    }
+
+    pub fn animate(&mut self, dt: f32) {
+        self.invulnerable_timer = (self.invulnerable_timer - dt).max(0.0);
+        let animation_dt = if self.is_dashing() { dt * DASH_ANIMATION_SPEEDUP } else { dt };
+        let events = self.sprite.animate(animation_dt);
+
+        // The run cycle's event frames are the ones where a foot touches down
+        self.stepped = matches!(self.sprite.current(), AnimationType::Run(_)) && !events.is_empty();
+
+        // A missing attack animation has nothing to wait for
+        if self.is_attacking() {
+            if self.sprite.is_finished() || !self.sprite.has_animation(self.sprite.current()) {
+                self.sprite.reset();
+                self.animation.finish();
+                self.sprite.change(self.animation.animation());
+            } else if !events.is_empty() {
+                self.hitbox_active = true;
+            }
+        }
+        if !self.is_attacking() {
+            self.hitbox_active = false;
+        }
+    }
+
+    // The area in front of the player the current swing can hit, if it's
+    // reached the point where it connects
+    pub fn active_hitbox(&self) -> Option<Rectangle> {
+        if !self.hitbox_active {
+            return None;
+        }
+
+        let reach = ATTACK_REACH * self.sprite.scale;
+        let c = self.collision;
+        Some(match self.sprite.current().direction() {
+            Direction::Up => Rectangle::new(c.x, c.y - reach, c.width, reach),
+            Direction::Down => Rectangle::new(c.x, c.y + c.height, c.width, reach),
+            Direction::Left => Rectangle::new(c.x - reach, c.y, reach, c.height),
+            Direction::Right => Rectangle::new(c.x + c.width, c.y, reach, c.height),
+            // The body's own size, pushed out past the corner
+            diagonal => {
+                let v = diagonal.to_vector();
+                Rectangle::new(c.x + v.x.signum() * reach, c.y + v.y.signum() * reach, c.width, c.height)
+            }
+        })
+    }
+
+    // Where to draw the player alpha of the way from the previous update to
+    // the latest. Only for drawing, everything else goes by pos.
+    pub fn render_pos(&self, alpha: f32) -> Vector2 {
+        self.prev_pos.lerp(self.pos, alpha)
+    }
+
+    pub fn render_center(&self, alpha: f32) -> Vector2 {
+        self.center() + self.render_pos(alpha) - self.pos
+    }
+
+    // Hidden for every other half blink while invulnerable. The animation
+    // keeps running in update either way.
+    fn blinked_out(&self) -> bool {
+        self.is_alive() && self.is_invulnerable() && self.blink_rate > 0.0
+            && (self.invulnerable_timer * self.blink_rate * 2.0) as u32 % 2 == 1
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, view: Rectangle, alpha: f32) {
+        if !self.blinked_out() {
+            self.sprite.draw(self.render_pos(alpha), view, d);
+        }
+    }
+
+    // HUD element, call outside of begin_mode2D so it ignores the camera
+    pub fn draw_health_bar(&self, d: &mut RaylibDrawHandle, bar: Rectangle) {
+        let fraction = self.health as f32 / self.max_health as f32;
+        d.draw_rectangle_rec(bar, Color::RED);
+        d.draw_rectangle_rec(Rectangle::new(bar.x, bar.y, bar.width * fraction, bar.height), Color::GREEN);
+        d.draw_rectangle_lines_ex(bar, 1.0, Color::BLACK);
+    }
+
+    // Picks the direction to face for a movement vector, one of eight by
+    // angle when the sprite has diagonal runs (see Direction::from_angle).
+    // Otherwise the dominant axis wins; on an exact diagonal the most
+    // recently pressed of the two keys wins, falling back to horizontal over
+    // vertical.
+    fn facing_for(&self, dir: Vector2) -> Direction {
+        let facing = Direction::from_angle(dir.y.atan2(dir.x));
+        if !facing.is_diagonal() || self.sprite.has_animation(AnimationType::Run(facing)) {
+            return facing;
+        }
+
+        let vertical = if dir.y < 0.0 { Direction::Up } else { Direction::Down };
+        if dir.x.abs() == dir.y.abs() && self.last_pressed == Some(vertical) {
+            vertical
+        } else {
+            direction_from_vector(dir, self.last_direction)
+        }
+    }
+
+    // Speeds up towards full speed along dir, or slows to a stop with no
+    // dir, then moves. A dash goes at its speed straight away and a swing
+    // stops the player dead. An aiming player keeps facing the aim while
+    // moving.
+    fn move_player(&mut self, dir: Vector2, aiming: bool, world: &World, dt: f32) {
+        // Normalize so diagonals don't cover more ground than a single axis
+        let dir = if dir.length() > 1.0 { dir.normalized() } else { dir };
+
+        if self.is_attacking() {
+            self.velocity = Vector2::zero();
+        } else if self.is_dashing() {
+            self.velocity = dir * self.speed * DASH_SPEED_MULTIPLIER;
+        } else {
+            // Whatever a dash left over is dropped rather than slid off
+            if self.velocity.length() > self.speed {
+                self.velocity = self.velocity.normalized() * self.speed;
+            }
+            let rate = if dir == Vector2::zero() { self.friction } else { self.acceleration };
+            self.velocity = approach(self.velocity, dir * self.speed, rate * dt);
+        }
+
+        let step = self.velocity * dt;
+        let moved = self.try_move(step, world.obstacles_for(self.filter));
+        // Speed into a wall isn't kept, so letting go beside one stops at once
+        if (moved.x - step.x).abs() > BLOCKED_TOLERANCE {
+            self.velocity.x = 0.0;
+        }
+        if (moved.y - step.y).abs() > BLOCKED_TOLERANCE {
+            self.velocity.y = 0.0;
+        }
+        self.clamp_to_bounds(world.bounds);
+
+        if dir != Vector2::zero() && !aiming {
+            self.last_direction = self.facing_for(dir);
+        }
+        // Goes by how fast the player is actually going, so the run plays on
+        // through the slowdown after the keys are let go
+        self.is_moving = self.velocity.length() > MOVING_THRESHOLD;
+        if self.is_moving {
+            self.request(AnimationState::Running);
+        } else {
+            self.request(AnimationState::Idle);
+        }
+    }
+
+    fn update_collision(&mut self) {
+        let scale = self.sprite.scale;
+        self.collision = Rectangle::new(
+            self.pos.x + self.collision_offset.x * scale,
+            self.pos.y + self.collision_offset.y * scale,
+            self.collision_size.x * scale,
+            self.collision_size.y * scale,
+        );
+    }
+
+    // Moves the player straight there, centering its collision box on center
+    pub fn teleport(&mut self, center: Vector2) {
+        self.update_collision();
+        self.pos += center - self.center();
+        self.prev_pos = self.pos;
+        self.update_collision();
+    }
+
+    pub fn snapshot(&self) -> PlayerSnapshot {
+        PlayerSnapshot {
+            pos: self.pos,
+            health: self.health,
+            max_health: self.max_health,
+            level: self.level,
+            xp: self.xp,
+            speed: self.speed,
+            inventory: self.inventory.slots().iter().flatten().copied().collect(),
+            animation: self.sprite.current(),
+        }
+    }
+
+    // Standing still, whatever it was doing when the snapshot was taken
+    pub fn restore(&mut self, state: &PlayerSnapshot) {
+        self.pos = state.pos;
+        self.prev_pos = state.pos;
+        self.velocity = Vector2::zero();
+        // Their times were on the clock being left behind
+        self.cooldowns = Cooldowns::new();
+        self.max_health = state.max_health.max(1);
+        self.health = state.health.clamp(1, self.max_health);
+        self.level = state.level;
+        self.xp = state.xp;
+        self.speed = state.speed;
+        self.inventory = Inventory::new();
+        for slot in &state.inventory {
+            self.inventory.add_item(slot.kind, slot.count);
+        }
+        self.last_direction = state.animation.direction();
+        self.force_animation(state.animation);
+        self.update_collision();
+    }
+
+    pub fn center(&self) -> Vector2 {
+        Vector2::new(
+            self.collision.x + self.collision.width / 2.0,
+            self.collision.y + self.collision.height / 2.0,
+        )
+    }
+
+    // Returns how far it actually got
+    fn try_move(&mut self, delta: Vector2, obstacles: &[Rectangle]) -> Vector2 {
+        self.update_collision();
+        let moved = move_and_collide(self.collision, delta, obstacles);
+        self.pos += moved;
+        self.update_collision();
+        moved
+    }
+
+    // Keeps the collision box, the part of the sprite that's actually the
+    // character, inside bounds
+    pub fn clamp_to_bounds(&mut self, bounds: Rectangle) {
+        self.update_collision();
+        let max_x = (bounds.x + bounds.width - self.collision.width).max(bounds.x);
+        let max_y = (bounds.y + bounds.height - self.collision.height).max(bounds.y);
+        self.pos.x += self.collision.x.clamp(bounds.x, max_x) - self.collision.x;
+        self.pos.y += self.collision.y.clamp(bounds.y, max_y) - self.collision.y;
+        self.update_collision();
+    }
+
+    pub fn handle_input(&mut self, input: &InputState, world: &World, dt: f32) {
+        self.prev_pos = self.pos;
+        self.dash_started = false;
+        self.fired = false;
+        let aim = self.aim.take()
+            .map(|target| target - self.center())
+            .filter(|to_target| *to_target != Vector2::zero());
+        if !self.is_alive() {
+            return;
+        }
+        if let Some(to_target) = aim {
+            self.last_direction = self.facing_for(to_target);
+        }
+
+        let mut movement = input.move_dir;
+        if let Some(dir) = input.pressed_direction {
+            self.last_pressed = Some(dir);
+        }
+
+        let push = self.knockback.step(dt);
+        if push != Vector2::zero() {
+            self.try_move(push, world.obstacles_for(self.filter));
+            self.clamp_to_bounds(world.bounds);
+        }
+
+        self.cooldowns.set_time(world.clock.elapsed);
+        self.dash_timer = (self.dash_timer - dt).max(0.0);
+        if input.dash_pressed && self.can_dash() {
+            // Full speed whichever way it's aimed, however far the stick is pushed
+            self.dash_direction = if movement != Vector2::zero() { movement.normalized() } else { self.last_direction.to_vector() };
+            self.dash_timer = DASH_TIME;
+            // The cooldown only starts counting once the dash itself is over
+            self.cooldowns.trigger(AbilityId::Dash, DASH_TIME + DASH_COOLDOWN);
+            self.dash_started = true;
+        }
+        if self.is_dashing() {
+            movement = self.dash_direction;
+        }
+
+        self.move_player(movement, aim.is_some(), world, dt);
+
+        if input.attack_pressed {
+            self.attack();
+        }
+        if input.fire_pressed && !self.is_attacking() && self.cooldowns.ready(AbilityId::Fire) {
+            self.cooldowns.trigger(AbilityId::Fire, FIRE_COOLDOWN);
+            self.fired = true;
+            self.fire_direction = aim.map_or(self.last_direction.to_vector(), |to_target| to_target.normalized());
+        }
+    }
+
+    pub fn apply_knockback(&mut self, dir: Vector2, strength: f32) {
+        self.knockback.apply(dir, strength);
+    }
+
+    pub fn can_dash(&self) -> bool {
+        self.is_alive() && !self.is_attacking() && !self.is_dashing() && self.cooldowns.ready(AbilityId::Dash)
+    }
+
+    fn is_dashing(&self) -> bool {
+        self.dash_timer > 0.0
+    }
+
+    // Starts a swing unless one is already in progress, the player stays put
+    // until it finishes
+    pub fn attack(&mut self) {
+        if !self.is_alive() || self.is_attacking() || !self.cooldowns.ready(AbilityId::Attack) {
+            return;
+        }
+        self.cooldowns.trigger(AbilityId::Attack, ATTACK_COOLDOWN);
+        self.request(AnimationState::Attacking);
+        self.swing += 1;
+    }
+
+    pub fn swing(&self) -> u32 {
+        self.swing
+    }
+}
+
+impl Drawable for Player {
+    fn depth(&self) -> f32 {
+        let bounds = self.sprite.bounds(self.pos);
+        bounds.y + bounds.height
+    }
+
+    fn render(&self, d: &mut RaylibDrawHandle, view: Rectangle, alpha: f32) {
+        self.draw(d, view, alpha);
+    }
+}
+
+// Input is handled separately through handle_input, updating only animates
+impl GameObject for Player {
+    fn update(&mut self, dt: f32, _world: &World) {
+        self.animate(dt);
+    }
+
+    fn bounds(&self) -> Rectangle {
+        self.collision
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::SpriteAnimationBuilder;
+    use crate::textures::blank_texture;
+
+    fn open_world() -> World {
+        World::new(Rectangle::new(-1000.0, -1000.0, 2000.0, 2000.0))
+    }
+
+    fn moving(dir: Vector2) -> InputState {
+        InputState { move_dir: dir, ..InputState::default() }
+    }
+
+    #[test]
+    fn movement_scales_with_dt() {
+        let world = open_world();
+        let right = moving(Vector2::new(1.0, 0.0));
+        let mut player = PlayerBuilder::new().build();
+        // Up to full speed first, so only dt differs between the two steps
+        for _ in 0..60 {
+            player.handle_input(&right, &world, 1.0 / 60.0);
+        }
+
+        let start = player.pos;
+        player.handle_input(&right, &world, 1.0 / 60.0);
+        let short = player.pos.x - start.x;
+        let start = player.pos;
+        player.handle_input(&right, &world, 1.0 / 30.0);
+        let long = player.pos.x - start.x;
+
+        assert!((short - player.speed / 60.0).abs() < 0.001);
+        assert!((long - short * 2.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn stops_flush_against_a_wall_and_slides_along_it() {
+        let mut world = open_world();
+        world.add_obstacle(50.0, -1000.0, 20.0, 2000.0);
+        let mut player = PlayerBuilder::new().build();
+        player.teleport(Vector2::zero());
+        let down_right = moving(Vector2::new(1.0, 1.0));
+        for _ in 0..120 {
+            player.handle_input(&down_right, &world, 1.0 / 60.0);
+        }
+
+        let bounds = player.bounds();
+        assert!((bounds.x + bounds.width - 50.0).abs() < 0.01);
+        assert!(bounds.y > 50.0);
+    }
+
+    #[test]
+    fn damage_clamps_at_zero_health() {
+        let mut player = PlayerBuilder::new().build();
+        assert!(player.is_alive());
+        player.take_damage(player.max_health + 3);
+        assert_eq!(player.health, 0);
+        assert!(!player.is_alive());
+    }
+
+    #[test]
+    fn invulnerability_blocks_the_next_hit() {
+        let mut player = PlayerBuilder::new().build();
+        player.take_damage(1);
+        player.take_damage(1);
+        assert_eq!(player.health, player.max_health - 1);
+
+        player.animate(INVULNERABILITY_TIME);
+        player.take_damage(1);
+        assert_eq!(player.health, player.max_health - 2);
+        assert!(player.is_alive());
+    }
+
+    #[test]
+    fn stops_at_the_corner_of_the_bounds() {
+        let world = World::new(Rectangle::new(0.0, 0.0, 200.0, 200.0));
+        let mut player = PlayerBuilder::new().build();
+        player.teleport(Vector2::new(100.0, 100.0));
+        let up_left = moving(Vector2::new(-1.0, -1.0));
+        for _ in 0..180 {
+            player.handle_input(&up_left, &world, 1.0 / 60.0);
+        }
+
+        let bounds = player.bounds();
+        assert!(bounds.x.abs() < 0.01);
+        assert!(bounds.y.abs() < 0.01);
+    }
+
+    #[test]
+    fn hitbox_is_only_out_on_the_active_frames() {
+        let mut player = PlayerBuilder::new().build();
+        let swing = SpriteAnimationBuilder::new(blank_texture(64, 16))
+            .frames(4)
+            .speed(10)
+            .looping(false)
+            .event_frames(vec![2])
+            .build();
+        player.sprite.add_animation(AnimationType::Attack1(Direction::Down), swing);
+
+        player.attack();
+        assert_eq!(player.current_animation_type(), AnimationType::Attack1(Direction::Down));
+        let mut out = Vec::new();
+        for _ in 0..4 {
+            player.animate(0.1);
+            out.push(player.active_hitbox().is_some());
+        }
+        // Frame 1, then the event frame 2, then the last frame ends the swing
+        assert_eq!(out, vec![false, true, false, false]);
+        assert!(!player.is_attacking());
+    }
+
+    #[test]
+    fn one_gain_can_level_up_twice() {
+        let mut player = PlayerBuilder::new().build();
+        player.take_damage(1);
+        let (health, speed) = (player.max_health, player.speed);
+
+        // 10 finishes level 1, 20 more level 2, leaving 5 towards level 3
+        assert_eq!(player.add_xp(35), 2);
+        assert_eq!(player.level, 3);
+        assert_eq!(player.xp, 5);
+        assert_eq!(player.xp_to_next(), 30);
+        assert_eq!(player.max_health, health + 2 * LEVEL_HEALTH_BONUS);
+        assert_eq!(player.speed, speed + 2.0 * LEVEL_SPEED_BONUS);
+        assert_eq!(player.health, player.max_health);
+    }
+
+    #[test]
+    fn velocity_ramps_up_then_decays_to_zero() {
+        let world = open_world();
+        let right = moving(Vector2::new(1.0, 0.0));
+        let mut player = PlayerBuilder::new().build();
+
+        let mut speeds = Vec::new();
+        for _ in 0..30 {
+            player.handle_input(&right, &world, 1.0 / 60.0);
+            speeds.push(player.velocity.x);
+        }
+        assert!(speeds[0] > 0.0 && speeds[0] < player.speed);
+        assert!(speeds.windows(2).all(|pair| pair[1] >= pair[0]));
+        assert_eq!(player.velocity.x, player.speed);
+        assert!(player.is_moving);
+
+        let mut speeds = Vec::new();
+        for _ in 0..30 {
+            player.handle_input(&InputState::default(), &world, 1.0 / 60.0);
+            speeds.push(player.velocity.x);
+        }
+        assert!(speeds[0] > 0.0 && speeds[0] < player.speed);
+        assert!(speeds.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert_eq!(player.velocity, Vector2::zero());
+        assert!(!player.is_moving);
+    }
+
+    #[test]
+    fn the_builder_sets_each_field() {
+        let player = PlayerBuilder::new()
+            .collision(Rectangle::new(4.0, 6.0, 10.0, 12.0))
+            .speed(90.0)
+            .acceleration(600.0)
+            .friction(800.0)
+            .scale(2.0)
+            .build();
+        assert_eq!(player.collision_offset, Vector2::new(4.0, 6.0));
+        assert_eq!(player.collision_size, Vector2::new(10.0, 12.0));
+        assert_eq!(player.bounds(), Rectangle::new(8.0, 12.0, 20.0, 24.0));
+        assert_eq!(player.speed, 90.0);
+        assert_eq!(player.acceleration, 600.0);
+        assert_eq!(player.friction, 800.0);
+        assert_eq!(player.sprite.scale, 2.0);
+    }
+
+    #[test]
+    fn dying_without_a_death_animation_has_nothing_to_wait_for() {
+        let mut player = PlayerBuilder::new().build();
+        player.take_damage(player.max_health);
+        assert!(player.death_finished());
+    }
+
+    #[test]
+    fn dying_waits_for_the_death_animation() {
+        let mut player = PlayerBuilder::new().build();
+        let fall = SpriteAnimationBuilder::new(blank_texture(64, 16)).frames(4).looping(false).build();
+        player.sprite.add_animation(AnimationType::Death(Direction::Down), fall);
+        player.take_damage(player.max_health);
+        player.animate(0.1);
+        assert!(!player.death_finished());
+        for _ in 0..3 {
+            player.animate(0.1);
+        }
+        assert!(player.death_finished());
+    }
+}
@@ -0,0 +1,125 @@
+// Something a Pool can hand out again once it's done with
+pub trait Poolable {
+    // Back to how a freshly made one starts, called before it's reused
+    fn reset(&mut self);
+    fn is_expired(&self) -> bool;
+}
+
+// A fixed number of slots for short-lived things, allocated as they're first
+// needed and then reused forever. The live ones are kept at the front, an
+// expired one is swapped to the back, so nothing is ever freed or moved
+// between allocations.
+pub struct Pool<T> {
+    items: Vec<T>, // live ones first, then the released ones waiting for reuse
+    live: usize,
+    capacity: usize,
+    make: Box<dyn Fn() -> T>,
+}
+
+impl<T: Poolable> Pool<T> {
+    pub fn new(capacity: usize, make: impl Fn() -> T + 'static) -> Pool<T> {
+        Pool { items: Vec::with_capacity(capacity), live: 0, capacity, make: Box::new(make) }
+    }
+
+    // A slot to fill in, or None when every slot is in use
+    pub fn acquire(&mut self) -> Option<&mut T> {
+        if self.live < self.items.len() {
+            self.items[self.live].reset();
+        } else if self.items.len() < self.capacity {
+            self.items.push((self.make)());
+        } else {
+            return None;
+        }
+        self.live += 1;
+        Some(&mut self.items[self.live - 1])
+    }
+
+    // Frees up every slot whose item has expired, call once they've updated
+    pub fn release_expired(&mut self) {
+        let mut i = 0;
+        while i < self.live {
+            if self.items[i].is_expired() {
+                self.live -= 1;
+                self.items.swap(i, self.live);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.live = 0;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items[..self.live].iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.items[..self.live].iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    struct Spark {
+        life: u32, // updates left
+    }
+
+    impl Poolable for Spark {
+        fn reset(&mut self) {
+            self.life = 0;
+        }
+
+        fn is_expired(&self) -> bool {
+            self.life == 0
+        }
+    }
+
+    fn counted_pool(capacity: usize) -> (Pool<Spark>, Rc<Cell<usize>>) {
+        let made = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&made);
+        let pool = Pool::new(capacity, move || {
+            counter.set(counter.get() + 1);
+            Spark { life: 0 }
+        });
+        (pool, made)
+    }
+
+    #[test]
+    fn freed_slots_are_reused() {
+        let (mut pool, made) = counted_pool(8);
+        for _ in 0..3 {
+            // A burst that all burns out
+            while let Some(spark) = pool.acquire() {
+                spark.life = 2;
+            }
+            assert_eq!(pool.iter().count(), 8);
+            for _ in 0..2 {
+                for spark in pool.iter_mut() {
+                    spark.life -= 1;
+                }
+                pool.release_expired();
+            }
+            assert_eq!(pool.iter().count(), 0);
+        }
+        assert_eq!(made.get(), 8);
+        assert_eq!(pool.items.len(), 8);
+    }
+
+    #[test]
+    fn never_grows_past_capacity() {
+        let (mut pool, made) = counted_pool(4);
+        let allocated = pool.items.capacity();
+        let acquired = (0..10).filter(|_| pool.acquire().map(|spark| spark.life = 1).is_some()).count();
+        assert_eq!(acquired, 4);
+        assert_eq!(made.get(), 4);
+        assert_eq!(pool.items.len(), 4);
+        assert_eq!(pool.items.capacity(), allocated);
+    }
+}
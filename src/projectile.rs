@@ -0,0 +1,108 @@
+use std::rc::Rc;
+
+use raylib::prelude::*;
+
+use crate::drawable::Drawable;
+use crate::object::GameObject;
+use crate::physics::{CollisionFilter, CollisionLayer};
+use crate::pool::Poolable;
+use crate::world::World;
+
+const SPEED: f32 = 360.0; // pixels per second
+const LIFETIME: f32 = 1.5; // seconds before it fizzles out
+const FRAMES_PER_SECOND: f32 = 12.0;
+const SCALE: f32 = 1.5;
+pub const MAX_PROJECTILES: usize = 64; // in flight at once, across every player
+
+// Shots fly through the players, only walls and enemies stop them
+pub const PROJECTILE_FILTER: CollisionFilter = CollisionFilter {
+    layer: CollisionLayer::PLAYER_SHOT,
+    mask: CollisionLayer::WALL.union(CollisionLayer::ENEMY),
+};
+
+// A shot flying in a straight line until it hits a wall or an enemy or runs
+// out of time. The sheet is square frames in a row, looping. They're pooled,
+// made spent and then launched when fired.
+pub struct Projectile {
+    pub pos: Vector2, // center
+    pub velocity: Vector2,
+    pub owner: usize, // index of the player who fired it, who gets the XP
+    age: f32,
+    pub spent: bool, // hit something or ran out, remove it
+    sheet: Rc<Texture2D>,
+    pub filter: CollisionFilter,
+}
+
+impl Projectile {
+    pub fn new(sheet: Rc<Texture2D>) -> Projectile {
+        Projectile { pos: Vector2::zero(), velocity: Vector2::zero(), owner: 0, age: 0.0, spent: true, sheet, filter: PROJECTILE_FILTER }
+    }
+
+    // direction is unit length
+    pub fn launch(&mut self, pos: Vector2, direction: Vector2, owner: usize) {
+        self.pos = pos;
+        self.velocity = direction * SPEED;
+        self.owner = owner;
+        self.age = 0.0;
+        self.spent = false;
+    }
+
+    fn size(&self) -> f32 {
+        self.sheet.height as f32 * SCALE
+    }
+}
+
+impl Poolable for Projectile {
+    // launch sets everything else
+    fn reset(&mut self) {
+        self.age = 0.0;
+        self.spent = false;
+    }
+
+    fn is_expired(&self) -> bool {
+        self.spent
+    }
+}
+
+impl Drawable for Projectile {
+    fn depth(&self) -> f32 {
+        self.pos.y + self.size() / 2.0
+    }
+
+    fn render(&self, d: &mut RaylibDrawHandle, view: Rectangle, _alpha: f32) {
+        let bounds = self.bounds();
+        if self.spent || !bounds.check_collision_recs(&view) {
+            return;
+        }
+
+        let frame_size = self.sheet.height as f32;
+        let frames = (self.sheet.width / self.sheet.height).max(1) as u32;
+        let frame = (self.age * FRAMES_PER_SECOND) as u32 % frames;
+        let source = Rectangle::new(frame as f32 * frame_size, 0.0, frame_size, frame_size);
+        d.draw_texture_pro(&*self.sheet, source, bounds, Vector2::zero(), 0.0, Color::WHITE);
+    }
+}
+
+impl GameObject for Projectile {
+    // Walls stop it dead, enemies are up to the game
+    fn update(&mut self, dt: f32, world: &World) {
+        if self.spent {
+            return;
+        }
+
+        self.age += dt;
+        self.pos += self.velocity * dt;
+        let bounds = self.bounds();
+        if self.age >= LIFETIME
+            || !bounds.check_collision_recs(&world.bounds)
+            || world.obstacles_for(self.filter).iter().any(|obstacle| obstacle.check_collision_recs(&bounds))
+        {
+            self.spent = true;
+        }
+    }
+
+    fn bounds(&self) -> Rectangle {
+        let size = self.size();
+        Rectangle::new(self.pos.x - size / 2.0, self.pos.y - size / 2.0, size, size)
+    }
+}
@@ -0,0 +1,184 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use raylib::prelude::*;
+
+use crate::animation::Direction;
+use crate::input::InputState;
+use crate::json::{self, Value};
+
+// Input logs are JSON lines, one { dt, players } object per update step with
+// a { move, direction, aim, ...pressed flags } entry for each player. Each is
+// flushed as it's written, so a log cut short by a crash still replays up to
+// it.
+
+fn input_to_json(input: &InputState) -> Value {
+    let flag = |name: &str, value: bool| (name.to_string(), Value::Bool(value));
+    Value::Object(vec![
+        ("move".to_string(), Value::Array(vec![
+            Value::Number(input.move_dir.x as f64),
+            Value::Number(input.move_dir.y as f64),
+        ])),
+        ("direction".to_string(), match input.pressed_direction {
+            Some(dir) => Value::String(dir.name().to_string()),
+            None => Value::Null,
+        }),
+        ("aim".to_string(), match input.aim {
+            Some(aim) => Value::Array(vec![Value::Number(aim.x as f64), Value::Number(aim.y as f64)]),
+            None => Value::Null,
+        }),
+        flag("attack", input.attack_pressed),
+        flag("fire", input.fire_pressed),
+        flag("dash", input.dash_pressed),
+        flag("pause", input.pause_pressed),
+        flag("confirm", input.confirm_pressed),
+        flag("mute", input.mute_pressed),
+        flag("debug", input.debug_pressed),
+        flag("inventory", input.inventory_pressed),
+        flag("minimap", input.minimap_pressed),
+        flag("interact", input.interact_pressed),
+    ])
+}
+
+fn frame_to_json(dt: f32, inputs: &[InputState]) -> Value {
+    Value::Object(vec![
+        ("dt".to_string(), Value::Number(dt as f64)),
+        ("players".to_string(), Value::Array(inputs.iter().map(input_to_json).collect())),
+    ])
+}
+
+fn input_from_json(player: &Value) -> Option<InputState> {
+    let flag = |name: &str| player.get(name).and_then(Value::as_bool);
+    let movement = player.get("move")?.as_array()?;
+    let pressed_direction = match player.get("direction")? {
+        Value::Null => None,
+        dir => Some(Direction::from_name(dir.as_str()?)?),
+    };
+    let aim = match player.get("aim")? {
+        Value::Null => None,
+        aim => {
+            let aim = aim.as_array()?;
+            Some(Vector2::new(aim.first()?.as_f64()? as f32, aim.get(1)?.as_f64()? as f32))
+        }
+    };
+    let input = InputState {
+        move_dir: Vector2::new(movement.first()?.as_f64()? as f32, movement.get(1)?.as_f64()? as f32),
+        pressed_direction,
+        aim,
+        attack_pressed: flag("attack")?,
+        fire_pressed: flag("fire")?,
+        dash_pressed: flag("dash")?,
+        pause_pressed: flag("pause")?,
+        confirm_pressed: flag("confirm")?,
+        mute_pressed: flag("mute")?,
+        debug_pressed: flag("debug")?,
+        inventory_pressed: flag("inventory")?,
+        minimap_pressed: flag("minimap")?,
+        interact_pressed: flag("interact")?,
+        // The window isn't part of what's replayed
+        fullscreen_pressed: false,
+    };
+    Some(input)
+}
+
+fn frame_from_json(frame: &Value) -> Option<(f32, Vec<InputState>)> {
+    let inputs = frame.get("players")?.as_array()?.iter().map(input_from_json).collect::<Option<Vec<_>>>()?;
+    Some((frame.get("dt")?.as_f64()? as f32, inputs))
+}
+
+pub struct Recorder {
+    out: BufWriter<File>,
+    path: String,
+}
+
+impl Recorder {
+    pub fn create(path: &str) -> Result<Recorder, String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Couldn't create input recording {}: {}", path, e))?;
+        Ok(Recorder { out: BufWriter::new(file), path: path.to_string() })
+    }
+
+    pub fn record(&mut self, dt: f32, inputs: &[InputState]) -> Result<(), String> {
+        writeln!(self.out, "{}", frame_to_json(dt, inputs))
+            .and_then(|_| self.out.flush())
+            .map_err(|e| format!("Couldn't write input recording {}: {}", self.path, e))
+    }
+}
+
+pub struct Replay {
+    frames: Vec<(f32, Vec<InputState>)>,
+    next: usize,
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read input recording {}: {}", path, e))?;
+        Replay::parse(&text, path)
+    }
+
+    // A last line without its newline was cut off partway through being
+    // written, that one is dropped rather than failing the whole log
+    fn parse(text: &str, path: &str) -> Result<Replay, String> {
+        let last = text.lines().count().saturating_sub(1);
+        let cut_off = !text.ends_with('\n');
+        let mut frames = Vec::new();
+        for (i, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let frame = json::parse(line).ok().and_then(|frame| frame_from_json(&frame));
+            match frame {
+                Some(frame) => frames.push(frame),
+                None if i == last && cut_off => {
+                    eprintln!("WARNING: Frame {} of {} was cut off, replaying up to it", i + 1, path);
+                }
+                None => return Err(format!("Couldn't read frame {} of {}", i + 1, path)),
+            }
+        }
+        Ok(Replay { frames, next: 0 })
+    }
+
+    // None once every recorded frame has been played
+    pub fn next_frame(&mut self) -> Option<(f32, Vec<InputState>)> {
+        let frame = self.frames.get(self.next)?.clone();
+        self.next += 1;
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(dt: f32) -> String {
+        let input = InputState { move_dir: Vector2::new(1.0, 0.0), attack_pressed: true, ..InputState::default() };
+        format!("{}\n", frame_to_json(dt, &[input]))
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let mut replay = Replay::parse(&(frame(0.5) + &frame(0.25)), "test").unwrap();
+        let (dt, inputs) = replay.next_frame().unwrap();
+        assert_eq!(dt, 0.5);
+        assert_eq!(inputs[0].move_dir, Vector2::new(1.0, 0.0));
+        assert!(inputs[0].attack_pressed);
+        assert_eq!(replay.next_frame().unwrap().0, 0.25);
+        assert!(replay.next_frame().is_none());
+    }
+
+    #[test]
+    fn a_cut_off_last_line_replays_up_to_it() {
+        let third = frame(0.125);
+        let text = frame(0.5) + &frame(0.25) + &third[..third.len() / 2];
+        let mut replay = Replay::parse(&text, "test").unwrap();
+        assert_eq!(replay.next_frame().unwrap().0, 0.5);
+        assert_eq!(replay.next_frame().unwrap().0, 0.25);
+        assert!(replay.next_frame().is_none());
+    }
+
+    #[test]
+    fn a_broken_line_before_the_end_is_an_error() {
+        let text = frame(0.5) + "{\"dt\": 0.5}\n" + &frame(0.25);
+        assert!(Replay::parse(&text, "test").is_err());
+        // Finished with its newline, so it wasn't cut off while being written
+        assert!(Replay::parse(&(frame(0.5) + "{\"dt\": 0.5}\n"), "test").is_err());
+    }
+}
@@ -0,0 +1,97 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// xorshift32: tiny, fast and plenty for scattering effects around. The same
+// seed always gives the same numbers, so a run can be played out again.
+pub struct Rng {
+    state: u32,
+}
+
+impl Rng {
+    // Small seeds would start out giving small numbers, so the seed is
+    // scrambled first. Zero would only ever produce zeros, so it's nudged off it.
+    pub fn new(seed: u32) -> Rng {
+        let state = seed.wrapping_mul(0x9E3779B9);
+        Rng { state: (state ^ state >> 16).max(1) }
+    }
+
+    // Different every run, for when nothing needs to be reproduced
+    pub fn from_clock() -> Rng {
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.subsec_nanos() ^ time.as_secs() as u32);
+        Rng::new(seed)
+    }
+
+    // Carries on exactly where the generator that had state left off
+    pub fn from_state(state: u32) -> Rng {
+        Rng { state: state.max(1) }
+    }
+
+    pub fn state(&self) -> u32 {
+        self.state
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        x
+    }
+
+    // In 0..1
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + self.next_f32() * (max - min)
+    }
+
+    // An index into weights, each as likely as its share of the total. None
+    // when there's nothing with a weight above 0 to pick.
+    pub fn pick_weighted(&mut self, weights: &[f32]) -> Option<usize> {
+        let total: f32 = weights.iter().filter(|&&weight| weight > 0.0).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut roll = self.range_f32(0.0, total);
+        for (i, &weight) in weights.iter().enumerate().filter(|(_, weight)| **weight > 0.0) {
+            if roll < weight {
+                return Some(i);
+            }
+            roll -= weight;
+        }
+        // Rounding can leave the roll just past the last one
+        weights.iter().rposition(|&weight| weight > 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(rng: &mut Rng) -> Vec<u32> {
+        (0..16).map(|_| rng.next_u32()).collect()
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_numbers() {
+        assert_eq!(sequence(&mut Rng::new(42)), sequence(&mut Rng::new(42)));
+        assert_eq!(sequence(&mut Rng::new(0)), sequence(&mut Rng::new(0)));
+    }
+
+    #[test]
+    fn different_seeds_give_different_numbers() {
+        assert_ne!(sequence(&mut Rng::new(1)), sequence(&mut Rng::new(2)));
+        assert_ne!(sequence(&mut Rng::new(0)), sequence(&mut Rng::new(1)));
+    }
+
+    #[test]
+    fn carries_on_from_a_saved_state() {
+        let mut rng = Rng::new(7);
+        rng.next_u32();
+        let mut resumed = Rng::from_state(rng.state());
+        assert_eq!(sequence(&mut resumed), sequence(&mut rng));
+    }
+}
@@ -0,0 +1,446 @@
+use raylib::prelude::*;
+
+use crate::animation::{AnimationType, Direction};
+use crate::inventory::{ItemKind, Slot};
+use crate::json::{self, Value};
+use crate::objective::Objective;
+use crate::world::GameClock;
+
+// Bumped whenever saves change in a way older ones can't be read as. Saves
+// from before there was a version count as 1.
+pub const SAVE_VERSION: u32 = 3;
+
+pub struct PlayerSnapshot {
+    pub pos: Vector2,
+    pub health: i32,
+    pub max_health: i32,
+    pub level: u32,
+    pub xp: u32,
+    pub speed: f32, // pixels per second, it goes up with each level
+    pub inventory: Vec<Slot>, // the filled slots, in order
+    pub animation: AnimationType,
+}
+
+pub struct EnemySnapshot {
+    pub pos: Vector2,
+    pub patrol: [Vector2; 2],
+    pub speed: f32,
+    pub health: i32,
+}
+
+pub struct ItemSnapshot {
+    pub kind: ItemKind,
+    pub center: Vector2,
+}
+
+// A level that's been left, as it was left
+pub struct LevelSnapshot {
+    pub map: String,
+    pub enemies: Vec<EnemySnapshot>,
+    pub items: Vec<ItemSnapshot>,
+    pub triggers: Vec<bool>,
+}
+
+pub struct CheckpointSnapshot {
+    pub map: String, // the level it's in
+    pub positions: Vec<Vector2>, // centers, one per player
+    pub health: Vec<i32>,
+}
+
+// Everything needed to pick a run back up where it was left: which map,
+// everyone on it and what's still lying around, which of its triggers have
+// gone off, the other levels as they were left, the last checkpoint, how far
+// the objectives have got, the clock and where the random numbers had got to. Textures aren't part of it,
+// restoring loads them again from the level's paths.
+pub struct WorldSnapshot {
+    pub map: String, // the level's map path
+    pub players: Vec<PlayerSnapshot>,
+    pub enemies: Vec<EnemySnapshot>, // only the living ones
+    pub items: Vec<ItemSnapshot>, // only the ones not picked up yet
+    pub triggers: Vec<bool>, // whether each of the map's triggers has fired, in the map's order
+    pub levels: Vec<LevelSnapshot>, // the others that have been left, not the one they're in
+    pub checkpoint: Option<CheckpointSnapshot>, // none reached yet
+    pub objectives: Vec<Objective>,
+    pub kills: u32,
+    pub clock: GameClock,
+    pub rng: u32, // the generator's state, see Rng::from_state
+}
+
+fn vector_value(v: Vector2) -> Value {
+    Value::Array(vec![Value::Number(v.x as f64), Value::Number(v.y as f64)])
+}
+
+fn vector_from(value: &Value) -> Option<Vector2> {
+    let pair = value.as_array()?;
+    Some(Vector2::new(pair.first()?.as_f64()? as f32, pair.get(1)?.as_f64()? as f32))
+}
+
+fn number(value: &Value, name: &str) -> Result<f64, String> {
+    value.get(name).and_then(Value::as_f64).ok_or(format!("missing \"{}\"", name))
+}
+
+// Not negative, and cut to a whole number
+fn count(value: &Value, name: &str) -> Result<u64, String> {
+    let n = number(value, name)?;
+    if n < 0.0 {
+        return Err(format!("\"{}\" is negative", name));
+    }
+    Ok(n as u64)
+}
+
+fn vector(value: &Value, name: &str) -> Result<Vector2, String> {
+    value.get(name).and_then(vector_from).ok_or(format!("missing \"{}\"", name))
+}
+
+fn list<'a>(value: &'a Value, name: &str) -> Result<&'a [Value], String> {
+    value.get(name).and_then(Value::as_array).ok_or(format!("missing \"{}\"", name))
+}
+
+fn fired_value(triggers: &[bool]) -> Value {
+    Value::Array(triggers.iter().map(|&fired| Value::Bool(fired)).collect())
+}
+
+fn fired(value: &Value) -> Result<Vec<bool>, String> {
+    list(value, "triggers")?.iter().map(Value::as_bool).collect::<Option<_>>()
+        .ok_or("a trigger's state isn't true or false".to_string())
+}
+
+impl PlayerSnapshot {
+    // { pos, health, max_health, level, xp, speed, inventory: [{ kind, count }], animation: { type, direction } }
+    fn to_value(&self) -> Value {
+        let inventory = self.inventory.iter().map(|slot| Value::Object(vec![
+            ("kind".to_string(), Value::String(slot.kind.name().to_string())),
+            ("count".to_string(), Value::Number(slot.count as f64)),
+        ])).collect();
+        Value::Object(vec![
+            ("pos".to_string(), vector_value(self.pos)),
+            ("health".to_string(), Value::Number(self.health as f64)),
+            ("max_health".to_string(), Value::Number(self.max_health as f64)),
+            ("level".to_string(), Value::Number(self.level as f64)),
+            ("xp".to_string(), Value::Number(self.xp as f64)),
+            ("speed".to_string(), Value::Number(self.speed as f64)),
+            ("inventory".to_string(), Value::Array(inventory)),
+            ("animation".to_string(), Value::Object(vec![
+                ("type".to_string(), Value::String(self.animation.name().to_string())),
+                ("direction".to_string(), Value::String(self.animation.direction().name().to_string())),
+            ])),
+        ])
+    }
+
+    fn from_value(value: &Value) -> Result<PlayerSnapshot, String> {
+        let health = number(value, "health")? as i32;
+        let max_health = count(value, "max_health")? as i32;
+        if health < 1 || health > max_health {
+            return Err("a player's health is out of range".to_string());
+        }
+
+        let inventory = list(value, "inventory")?.iter()
+            .map(|slot| {
+                let kind = slot.get("kind").and_then(Value::as_str)
+                    .and_then(ItemKind::from_name)
+                    .ok_or("an inventory slot has no valid \"kind\"")?;
+                let count = count(slot, "count")? as u32;
+                if count == 0 || count > kind.max_stack() {
+                    return Err("an inventory slot holds too few or too many".to_string());
+                }
+                Ok(Slot { kind, count })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let animation = value.get("animation").ok_or("missing \"animation\"")?;
+        let direction = animation.get("direction").and_then(Value::as_str)
+            .and_then(Direction::from_name)
+            .ok_or("no valid animation direction")?;
+        let animation = animation.get("type").and_then(Value::as_str)
+            .and_then(|name| AnimationType::from_name(name, direction))
+            .ok_or("no valid animation type")?;
+
+        Ok(PlayerSnapshot {
+            pos: vector(value, "pos")?,
+            health,
+            max_health,
+            level: (count(value, "level")? as u32).max(1),
+            xp: count(value, "xp")? as u32,
+            speed: number(value, "speed")? as f32,
+            inventory,
+            animation,
+        })
+    }
+}
+
+impl EnemySnapshot {
+    // { pos, patrol: [start, end], speed, health }
+    fn to_value(&self) -> Value {
+        Value::Object(vec![
+            ("pos".to_string(), vector_value(self.pos)),
+            ("patrol".to_string(), Value::Array(self.patrol.iter().copied().map(vector_value).collect())),
+            ("speed".to_string(), Value::Number(self.speed as f64)),
+            ("health".to_string(), Value::Number(self.health as f64)),
+        ])
+    }
+
+    fn from_value(value: &Value) -> Result<EnemySnapshot, String> {
+        let patrol = list(value, "patrol")?.iter().map(vector_from).collect::<Option<Vec<_>>>();
+        let Some(&[start, end]) = patrol.as_deref() else {
+            return Err("an enemy's \"patrol\" isn't two points".to_string());
+        };
+        let health = number(value, "health")? as i32;
+        if health < 1 {
+            return Err("an enemy is already dead".to_string());
+        }
+        Ok(EnemySnapshot {
+            pos: vector(value, "pos")?,
+            patrol: [start, end],
+            speed: number(value, "speed")? as f32,
+            health,
+        })
+    }
+}
+
+impl ItemSnapshot {
+    // { kind, center }
+    fn to_value(&self) -> Value {
+        Value::Object(vec![
+            ("kind".to_string(), Value::String(self.kind.name().to_string())),
+            ("center".to_string(), vector_value(self.center)),
+        ])
+    }
+
+    fn from_value(value: &Value) -> Result<ItemSnapshot, String> {
+        let kind = value.get("kind").and_then(Value::as_str)
+            .and_then(ItemKind::from_name)
+            .ok_or("an item has no valid \"kind\"")?;
+        Ok(ItemSnapshot { kind, center: vector(value, "center")? })
+    }
+}
+
+impl LevelSnapshot {
+    // { map, enemies, items, triggers }
+    fn to_value(&self) -> Value {
+        Value::Object(vec![
+            ("map".to_string(), Value::String(self.map.clone())),
+            ("enemies".to_string(), Value::Array(self.enemies.iter().map(EnemySnapshot::to_value).collect())),
+            ("items".to_string(), Value::Array(self.items.iter().map(ItemSnapshot::to_value).collect())),
+            ("triggers".to_string(), fired_value(&self.triggers)),
+        ])
+    }
+
+    fn from_value(value: &Value) -> Result<LevelSnapshot, String> {
+        let map = value.get("map").and_then(Value::as_str).ok_or("a level is missing \"map\"")?;
+        Ok(LevelSnapshot {
+            map: map.to_string(),
+            enemies: list(value, "enemies")?.iter().map(EnemySnapshot::from_value).collect::<Result<_, _>>()?,
+            items: list(value, "items")?.iter().map(ItemSnapshot::from_value).collect::<Result<_, _>>()?,
+            triggers: fired(value)?,
+        })
+    }
+}
+
+impl CheckpointSnapshot {
+    // { map, positions, health }
+    fn to_value(&self) -> Value {
+        Value::Object(vec![
+            ("map".to_string(), Value::String(self.map.clone())),
+            ("positions".to_string(), Value::Array(self.positions.iter().copied().map(vector_value).collect())),
+            ("health".to_string(), Value::Array(self.health.iter().map(|&health| Value::Number(health as f64)).collect())),
+        ])
+    }
+
+    fn from_value(value: &Value) -> Result<CheckpointSnapshot, String> {
+        let map = value.get("map").and_then(Value::as_str).ok_or("the checkpoint is missing \"map\"")?;
+        let positions = list(value, "positions")?.iter().map(vector_from).collect::<Option<Vec<_>>>()
+            .ok_or("a checkpoint position isn't a point")?;
+        let health = list(value, "health")?.iter()
+            .map(|health| health.as_f64().map(|health| health as i32).filter(|&health| health >= 1))
+            .collect::<Option<Vec<_>>>()
+            .ok_or("a checkpoint's health is out of range")?;
+        if positions.len() != health.len() {
+            return Err("the checkpoint's positions and health don't match up".to_string());
+        }
+        Ok(CheckpointSnapshot { map: map.to_string(), positions, health })
+    }
+}
+
+impl WorldSnapshot {
+    // { version, map, players, enemies, items, triggers, levels, checkpoint, objectives, kills, tick, elapsed, rng },
+    // checkpoint is null when there isn't one
+    pub fn to_value(&self) -> Value {
+        Value::Object(vec![
+            ("version".to_string(), Value::Number(SAVE_VERSION as f64)),
+            ("map".to_string(), Value::String(self.map.clone())),
+            ("players".to_string(), Value::Array(self.players.iter().map(PlayerSnapshot::to_value).collect())),
+            ("enemies".to_string(), Value::Array(self.enemies.iter().map(EnemySnapshot::to_value).collect())),
+            ("items".to_string(), Value::Array(self.items.iter().map(ItemSnapshot::to_value).collect())),
+            ("triggers".to_string(), fired_value(&self.triggers)),
+            ("levels".to_string(), Value::Array(self.levels.iter().map(LevelSnapshot::to_value).collect())),
+            ("checkpoint".to_string(), self.checkpoint.as_ref().map_or(Value::Null, CheckpointSnapshot::to_value)),
+            ("objectives".to_string(), Value::Array(self.objectives.iter().map(Objective::to_value).collect())),
+            ("kills".to_string(), Value::Number(self.kills as f64)),
+            ("tick".to_string(), Value::Number(self.clock.tick as f64)),
+            ("elapsed".to_string(), Value::Number(self.clock.elapsed as f64)),
+            ("rng".to_string(), Value::Number(self.rng as f64)),
+        ])
+    }
+
+    // A save from any other version is turned away rather than half read
+    pub fn from_value(value: &Value) -> Result<WorldSnapshot, String> {
+        let version = value.get("version").map_or(Some(1.0), Value::as_f64)
+            .ok_or("\"version\" isn't a number")?;
+        if version != SAVE_VERSION as f64 {
+            return Err(format!("it's from version {} and only version {} can be loaded", version, SAVE_VERSION));
+        }
+
+        let map = value.get("map").and_then(Value::as_str).ok_or("missing \"map\"")?;
+        let players = list(value, "players")?.iter().map(PlayerSnapshot::from_value).collect::<Result<Vec<_>, _>>()?;
+        if players.is_empty() {
+            return Err("there are no players".to_string());
+        }
+        let checkpoint = match value.get("checkpoint").ok_or("missing \"checkpoint\"")? {
+            Value::Null => None,
+            checkpoint => Some(CheckpointSnapshot::from_value(checkpoint)?),
+        };
+        Ok(WorldSnapshot {
+            map: map.to_string(),
+            players,
+            enemies: list(value, "enemies")?.iter().map(EnemySnapshot::from_value).collect::<Result<_, _>>()?,
+            items: list(value, "items")?.iter().map(ItemSnapshot::from_value).collect::<Result<_, _>>()?,
+            triggers: fired(value)?,
+            levels: list(value, "levels")?.iter().map(LevelSnapshot::from_value).collect::<Result<_, _>>()?,
+            checkpoint,
+            objectives: list(value, "objectives")?.iter().map(Objective::from_value).collect::<Result<_, _>>()?,
+            kills: count(value, "kills")? as u32,
+            clock: GameClock { tick: count(value, "tick")?, elapsed: number(value, "elapsed")?.max(0.0) as f32 },
+            rng: u32::try_from(count(value, "rng")?).map_err(|_| "\"rng\" is out of range")?,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_value().to_string())
+            .map_err(|e| format!("Couldn't write save {}: {}", path, e))
+    }
+
+    pub fn load(path: &str) -> Result<WorldSnapshot, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read save {}: {}", path, e))?;
+        let value = json::parse(&text)
+            .map_err(|e| format!("Couldn't parse save {}: {}", path, e))?;
+        WorldSnapshot::from_value(&value)
+            .map_err(|e| format!("Couldn't load save {}: {}", path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation_controller::AnimationState;
+    use crate::player::PlayerBuilder;
+
+    #[test]
+    fn player_round_trips() {
+        let mut player = PlayerBuilder::new().build();
+        player.teleport(Vector2::new(120.0, 340.0));
+        player.take_damage(2);
+        player.inventory.add_item(ItemKind::Coin, 7);
+        player.request(AnimationState::Running);
+
+        let text = player.snapshot().to_value().to_string();
+        let saved = PlayerSnapshot::from_value(&json::parse(&text).unwrap()).unwrap();
+        let mut loaded = PlayerBuilder::new().build();
+        loaded.restore(&saved);
+
+        assert_eq!(loaded.pos, player.pos);
+        assert_eq!(loaded.health, player.health);
+        assert_eq!(loaded.inventory.count(ItemKind::Coin), 7);
+        assert_eq!(loaded.current_animation_type(), player.current_animation_type());
+    }
+
+    #[test]
+    fn partial_save_is_an_error() {
+        let path = std::env::temp_dir().join("partial_save_test.json");
+        std::fs::write(&path, "{\"version\": 3, \"map\": \"resources/le").unwrap();
+        let loaded = WorldSnapshot::load(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert!(loaded.is_err());
+    }
+
+    fn world() -> WorldSnapshot {
+        let mut players: Vec<PlayerSnapshot> = (0..2).map(|_| PlayerBuilder::new().build().snapshot()).collect();
+        players[1].pos = Vector2::new(64.0, 96.0);
+        WorldSnapshot {
+            map: "resources/level1.tmj".to_string(),
+            players,
+            enemies: vec![
+                EnemySnapshot { pos: Vector2::new(40.0, 300.0), patrol: [Vector2::new(40.0, 300.0), Vector2::new(460.0, 300.0)], speed: 90.0, health: 3 },
+                EnemySnapshot { pos: Vector2::new(680.0, 120.5), patrol: [Vector2::new(680.0, 40.0), Vector2::new(680.0, 440.0)], speed: 90.0, health: 1 },
+            ],
+            items: vec![ItemSnapshot { kind: ItemKind::Coin, center: Vector2::new(12.0, 34.0) }],
+            triggers: vec![true, false],
+            levels: vec![LevelSnapshot {
+                map: "resources/level2.tmj".to_string(),
+                enemies: Vec::new(),
+                items: vec![ItemSnapshot { kind: ItemKind::Coin, center: Vector2::new(300.0, 80.0) }],
+                triggers: vec![true],
+            }],
+            checkpoint: Some(CheckpointSnapshot {
+                map: "resources/level2.tmj".to_string(),
+                positions: vec![Vector2::new(200.0, 100.0), Vector2::new(220.0, 100.0)],
+                health: vec![5, 2],
+            }),
+            objectives: Vec::new(),
+            kills: 4,
+            clock: GameClock { tick: 600, elapsed: 10.0 },
+            rng: 12345,
+        }
+    }
+
+    #[test]
+    fn world_round_trips() {
+        let saved = world();
+        let text = saved.to_value().to_string();
+        let loaded = WorldSnapshot::from_value(&json::parse(&text).unwrap()).unwrap();
+
+        assert_eq!(loaded.map, saved.map);
+        assert_eq!(loaded.players.len(), 2);
+        assert!(loaded.players.iter().zip(&saved.players).all(|(a, b)| a.pos == b.pos));
+        assert_eq!(loaded.enemies.len(), 2);
+        assert!(loaded.enemies.iter().zip(&saved.enemies).all(|(a, b)| a.pos == b.pos && a.patrol == b.patrol));
+        assert_eq!(loaded.items.len(), 1);
+        assert_eq!(loaded.items[0].center, saved.items[0].center);
+        assert_eq!(loaded.triggers, saved.triggers);
+        assert_eq!(loaded.levels.len(), 1);
+        assert_eq!(loaded.levels[0].map, "resources/level2.tmj");
+        assert!(loaded.levels[0].enemies.is_empty());
+        assert_eq!(loaded.levels[0].items[0].center, saved.levels[0].items[0].center);
+        assert_eq!(loaded.levels[0].triggers, vec![true]);
+        let checkpoint = loaded.checkpoint.unwrap();
+        assert_eq!(checkpoint.map, "resources/level2.tmj");
+        assert_eq!(checkpoint.positions, saved.checkpoint.as_ref().unwrap().positions);
+        assert_eq!(checkpoint.health, vec![5, 2]);
+        assert_eq!(loaded.clock, saved.clock);
+        assert_eq!(loaded.rng, saved.rng);
+    }
+
+    #[test]
+    fn no_checkpoint_round_trips_as_none() {
+        let saved = WorldSnapshot { checkpoint: None, ..world() };
+        let loaded = WorldSnapshot::from_value(&saved.to_value()).unwrap();
+        assert!(loaded.checkpoint.is_none());
+    }
+
+    #[test]
+    fn other_versions_are_turned_away() {
+        let Value::Object(fields) = world().to_value() else { unreachable!() };
+        let with_version = |version: Option<f64>| {
+            let mut fields: Vec<_> = fields.iter().filter(|(name, _)| name != "version").cloned().collect();
+            if let Some(version) = version {
+                fields.push(("version".to_string(), Value::Number(version)));
+            }
+            WorldSnapshot::from_value(&Value::Object(fields))
+        };
+        assert!(with_version(Some(SAVE_VERSION as f64)).is_ok());
+        assert!(with_version(Some(2.0)).is_err());
+        assert!(with_version(Some(SAVE_VERSION as f64 + 1.0)).is_err());
+        // From before saves had a version
+        assert!(with_version(None).is_err());
+    }
+}
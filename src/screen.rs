@@ -0,0 +1,94 @@
+use raylib::core::window::{get_current_monitor, get_monitor_height, get_monitor_width};
+use raylib::prelude::*;
+
+// The game always draws at this size, the window just shows it scaled
+pub const VIRTUAL_WIDTH: i32 = 640;
+pub const VIRTUAL_HEIGHT: i32 = 480;
+
+// How the virtual screen is scaled up to the window
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ScalingMode {
+    Integer, // the largest whole number that fits, so pixels stay square and even
+    Fit, // as large as fits, keeping the shape but not every pixel the same size
+    Stretch, // fills the window, squashing it to the window's shape
+}
+
+impl ScalingMode {
+    pub fn from_name(name: &str) -> Option<ScalingMode> {
+        match name {
+            "integer" => Some(Self::Integer),
+            "fit" => Some(Self::Fit),
+            "stretch" => Some(Self::Stretch),
+            _ => None,
+        }
+    }
+}
+
+// How the virtual screen sits inside the window: scaled up by mode and
+// centered, with black bars filling whatever it leaves
+pub struct Letterbox {
+    pub scale: Vector2, // window pixels per virtual pixel, only unequal when stretched
+    pub offset: Vector2, // top left of the scaled image, in window pixels
+}
+
+impl Letterbox {
+    // The window is kept at least the virtual size, so there's always room
+    // for a scale of 1
+    pub fn fit(mode: ScalingMode, window_width: i32, window_height: i32) -> Letterbox {
+        let x = window_width as f32 / VIRTUAL_WIDTH as f32;
+        let y = window_height as f32 / VIRTUAL_HEIGHT as f32;
+        let scale = match mode {
+            ScalingMode::Integer => Vector2::one() * x.min(y).floor().max(1.0),
+            ScalingMode::Fit => Vector2::one() * x.min(y),
+            ScalingMode::Stretch => Vector2::new(x, y),
+        };
+        // Rounded so the image starts on a whole window pixel
+        let offset = Vector2::new(
+            ((window_width as f32 - VIRTUAL_WIDTH as f32 * scale.x) / 2.0).floor(),
+            ((window_height as f32 - VIRTUAL_HEIGHT as f32 * scale.y) / 2.0).floor(),
+        );
+        Letterbox { scale, offset }
+    }
+
+    // Where the render target lands in the window
+    pub fn dest(&self) -> Rectangle {
+        Rectangle::new(
+            self.offset.x,
+            self.offset.y,
+            VIRTUAL_WIDTH as f32 * self.scale.x,
+            VIRTUAL_HEIGHT as f32 * self.scale.y,
+        )
+    }
+
+    // raylib applies this to every mouse query, so get_mouse_position reports
+    // virtual coordinates and nothing reading it has to know about the bars
+    // or the scaling
+    pub fn map_mouse(&self, rl: &mut RaylibHandle) {
+        rl.set_mouse_offset(Vector2::new(-self.offset.x, -self.offset.y));
+        rl.set_mouse_scale(1.0 / self.scale.x, 1.0 / self.scale.y);
+    }
+}
+
+// Fullscreen takes over the monitor's resolution, so the window size from
+// before is kept to go back to
+pub struct WindowMode {
+    windowed_size: (i32, i32),
+}
+
+impl WindowMode {
+    pub fn new(windowed_width: i32, windowed_height: i32) -> WindowMode {
+        WindowMode { windowed_size: (windowed_width, windowed_height) }
+    }
+
+    pub fn toggle_fullscreen(&mut self, rl: &mut RaylibHandle) {
+        if rl.is_window_fullscreen() {
+            rl.toggle_fullscreen();
+            rl.set_window_size(self.windowed_size.0, self.windowed_size.1);
+        } else {
+            self.windowed_size = (rl.get_screen_width(), rl.get_screen_height());
+            let monitor = get_current_monitor();
+            rl.set_window_size(get_monitor_width(monitor), get_monitor_height(monitor));
+            rl.toggle_fullscreen();
+        }
+    }
+}
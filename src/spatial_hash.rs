@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use raylib::prelude::*;
+
+// Buckets things by the square cells of a uniform grid their bounds touch,
+// so an overlap check only has to look at what shares a cell with it rather
+// than at everything. Things are known by their index in whatever list the
+// caller keeps. Meant to be cleared and filled again each update, the
+// buckets keep their allocations between fills.
+pub struct SpatialHash {
+    cell_size: f32, // pixels
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> SpatialHash {
+        SpatialHash { cell_size, cells: HashMap::new() }
+    }
+
+    pub fn clear(&mut self) {
+        for bucket in self.cells.values_mut() {
+            bucket.clear();
+        }
+    }
+
+    pub fn insert(&mut self, id: usize, bounds: Rectangle) {
+        for cell in self.cells_of(bounds) {
+            self.cells.entry(cell).or_default().push(id);
+        }
+    }
+
+    // Everything sharing a cell with rect, in ascending order and each only
+    // once. These only might overlap it, the caller still has to check.
+    pub fn query(&self, rect: Rectangle) -> Vec<usize> {
+        let mut found: Vec<usize> = self.cells_of(rect)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+
+    fn cells_of(&self, rect: Rectangle) -> impl Iterator<Item = (i32, i32)> + use<> {
+        let cell = |v: f32| (v / self.cell_size).floor() as i32;
+        let (left, top) = (cell(rect.x), cell(rect.y));
+        let (right, bottom) = (cell(rect.x + rect.width), cell(rect.y + rect.height));
+        (top..=bottom).flat_map(move |y| (left..=right).map(move |x| (x, y)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    #[test]
+    fn finds_every_overlap_a_naive_check_does() {
+        let mut rng = Rng::new(93);
+        let rects: Vec<Rectangle> = (0..300).map(|_| Rectangle::new(
+            rng.range_f32(-500.0, 500.0),
+            rng.range_f32(-500.0, 500.0),
+            rng.range_f32(1.0, 80.0),
+            rng.range_f32(1.0, 80.0),
+        )).collect();
+
+        let mut hash = SpatialHash::new(64.0);
+        for (i, &rect) in rects.iter().enumerate() {
+            hash.insert(i, rect);
+        }
+
+        for (i, rect) in rects.iter().enumerate() {
+            let naive: Vec<usize> = (0..rects.len()).filter(|&j| j != i && rect.check_collision_recs(&rects[j])).collect();
+            let hashed: Vec<usize> = hash.query(*rect).into_iter()
+                .filter(|&j| j != i && rect.check_collision_recs(&rects[j]))
+                .collect();
+            assert_eq!(hashed, naive);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use raylib::prelude::*;
+
+use crate::enemy::EnemyKind;
+use crate::rng::Rng;
+use crate::tilemap::TileMap;
+
+// An "enemy_spawn" point in the map, with the "enemy" it holds ("grunt" by
+// default) and a "weight" for how likely it is to be picked, 1 by default
+pub struct EnemySpawn {
+    pub kind: EnemyKind,
+    pub pos: Vector2, // where the middle of the enemy goes, moved off anything solid
+}
+
+// Up to cap of the map's enemy spawn points, each picked at most once and the
+// heavier ones more often. A weight of 0 turns a point off.
+pub fn pick_enemy_spawns(map: &TileMap, rng: &mut Rng, cap: usize) -> Result<Vec<EnemySpawn>, String> {
+    let mut spawns = Vec::new();
+    let mut weights = Vec::new();
+    for object in map.objects_of_kind("enemy_spawn") {
+        let name = object.property("enemy").map(|enemy| enemy.as_str()
+            .ok_or("Enemy spawn's \"enemy\" should be a string"))
+            .transpose()?
+            .unwrap_or("grunt");
+        let kind = EnemyKind::from_name(name)
+            .ok_or(format!("Unknown enemy \"{}\" at a spawn point", name))?;
+        let weight = object.property("weight").map(|weight| weight.as_f64()
+            .filter(|&weight| weight >= 0.0)
+            .ok_or("Enemy spawn has an invalid \"weight\""))
+            .transpose()?
+            .unwrap_or(1.0);
+        let point = Vector2::new(object.rect.x, object.rect.y);
+        let pos = map.nearest_open(point)
+            .ok_or(format!("Enemy spawn at {}, {} has nowhere open to stand", point.x, point.y))?;
+        spawns.push(EnemySpawn { kind, pos });
+        weights.push(weight as f32);
+    }
+
+    let mut picked = Vec::new();
+    while picked.len() < cap && let Some(i) = rng.pick_weighted(&weights) {
+        weights.swap_remove(i);
+        picked.push(spawns.swap_remove(i));
+    }
+    Ok(picked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::Value;
+    use crate::tilemap::MapObject;
+
+    const ROOM: &str = "\
+........
+........
+...#....
+........";
+
+    // A spawn point at each x along the top row, with each weight
+    fn map(weights: &[f64]) -> TileMap {
+        let mut map = TileMap::parse(ROOM, 32.0).unwrap();
+        for (i, &weight) in weights.iter().enumerate() {
+            map.objects.push(MapObject {
+                name: String::new(),
+                kind: "enemy_spawn".to_string(),
+                rect: Rectangle::new(i as f32 * 32.0 + 16.0, 16.0, 0.0, 0.0),
+                properties: vec![("weight".to_string(), Value::Number(weight))],
+            });
+        }
+        map
+    }
+
+    fn picked_xs(map: &TileMap, seed: u32, cap: usize) -> Vec<f32> {
+        pick_enemy_spawns(map, &mut Rng::new(seed), cap).unwrap().iter().map(|spawn| spawn.pos.x).collect()
+    }
+
+    #[test]
+    fn the_same_seed_picks_the_same_spawns() {
+        let map = map(&[1.0, 2.0, 3.0, 1.0, 5.0, 1.0]);
+        assert_eq!(picked_xs(&map, 7, 3), picked_xs(&map, 7, 3));
+        let differ = (0..20).any(|seed| picked_xs(&map, seed, 3) != picked_xs(&map, 7, 3));
+        assert!(differ);
+    }
+
+    #[test]
+    fn never_more_than_the_cap() {
+        let map = map(&[1.0; 6]);
+        for cap in 0..8 {
+            assert_eq!(picked_xs(&map, 1, cap).len(), cap.min(6));
+        }
+    }
+
+    #[test]
+    fn a_weight_of_zero_is_never_picked() {
+        let map = map(&[1.0, 0.0, 1.0, 0.0]);
+        for seed in 0..50 {
+            let picked = picked_xs(&map, seed, 4);
+            assert_eq!(picked.len(), 2);
+            assert!(picked.iter().all(|&x| x == 16.0 || x == 80.0));
+        }
+    }
+
+    #[test]
+    fn a_point_on_a_wall_is_moved_off_it() {
+        let mut map = map(&[]);
+        map.objects.push(MapObject {
+            name: String::new(),
+            kind: "enemy_spawn".to_string(),
+            rect: Rectangle::new(100.0, 70.0, 0.0, 0.0),
+            properties: Vec::new(),
+        });
+        let spawns = pick_enemy_spawns(&map, &mut Rng::new(1), 1).unwrap();
+        let pos = spawns[0].pos;
+        assert!(!map.is_solid_cell((pos.x / 32.0) as i64, (pos.y / 32.0) as i64));
+        assert!(pos.distance_to(Vector2::new(100.0, 70.0)) <= 32.0);
+    }
+}
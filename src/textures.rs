@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use raylib::prelude::*;
+
+// Loads each texture file once and shares it. Texture2D unloads itself from
+// the GPU when dropped, so handing out Rc clones keeps a texture alive for as
+// long as any animation still draws from it, and the cache's own clone keeps
+// it around for the next load of the same path. Everything holding one must be
+// dropped before the RaylibHandle closes the window.
+pub struct TextureManager {
+    textures: HashMap<String, Rc<Texture2D>>,
+    files: HashMap<String, LoadedFile>, // the textures that came from files, for reload_changed
+}
+
+struct LoadedFile {
+    modified: Option<SystemTime>, // as of the last successful load
+    filter: TextureFilter,
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+impl TextureManager {
+    pub fn new() -> TextureManager {
+        TextureManager { textures: HashMap::new(), files: HashMap::new() }
+    }
+
+    // Filtered for pixel art, so upscaled sprites stay crisp
+    pub fn load(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread, path: &str) -> Result<Rc<Texture2D>, String> {
+        self.load_with_filter(rl, thread, path, TextureFilter::TEXTURE_FILTER_POINT)
+    }
+
+    // The filter is set when the file is first loaded, later loads of the same
+    // path share that texture and its filter
+    pub fn load_with_filter(&mut self,
+        rl: &mut RaylibHandle, thread: &RaylibThread,
+        path: &str,
+        filter: TextureFilter) -> Result<Rc<Texture2D>, String>
+    {
+        if let Some(texture) = self.textures.get(path) {
+            return Ok(Rc::clone(texture));
+        }
+
+        let modified = modified_time(path);
+        let texture = rl.load_texture(thread, path)
+            .map_err(|e| format!("Couldn't load texture {}: {}", path, e))?;
+        self.files.insert(path.to_string(), LoadedFile { modified, filter });
+        Ok(self.insert(thread, path, texture, filter))
+    }
+
+    // Loads every file again whose modification time has changed since it
+    // was last loaded, with the filter it had, and returns each old texture
+    // with its replacement for whatever still draws from the old one. A file
+    // that won't load, like one still being written, keeps its old texture
+    // and is tried again on the next call.
+    pub fn reload_changed(&mut self, rl: &mut RaylibHandle, thread: &RaylibThread) -> Vec<(Rc<Texture2D>, Rc<Texture2D>)> {
+        let mut reloaded = Vec::new();
+        for (path, file) in &mut self.files {
+            let modified = modified_time(path);
+            if modified.is_none() || modified == file.modified {
+                continue;
+            }
+            match rl.load_texture(thread, path) {
+                Ok(texture) => {
+                    texture.set_texture_filter(thread, file.filter);
+                    file.modified = modified;
+                    let texture = Rc::new(texture);
+                    if let Some(old) = self.textures.insert(path.clone(), Rc::clone(&texture)) {
+                        reloaded.push((old, texture));
+                    }
+                }
+                Err(e) => eprintln!("WARNING: Couldn't reload texture {}: {}, trying again next time", path, e),
+            }
+        }
+        reloaded
+    }
+
+    // For images built into the executable with include_bytes!, decoded once
+    // and shared under name like a file would be under its path. file_type is
+    // the extension the bytes were saved with, like ".png".
+    pub fn load_from_memory(&mut self,
+        rl: &mut RaylibHandle, thread: &RaylibThread,
+        name: &str,
+        file_type: &str,
+        bytes: &[u8]) -> Result<Rc<Texture2D>, String>
+    {
+        if let Some(texture) = self.textures.get(name) {
+            return Ok(Rc::clone(texture));
+        }
+
+        let image = Image::load_image_from_mem(file_type, bytes)
+            .map_err(|e| format!("Couldn't decode texture {}: {}", name, e))?;
+        let texture = rl.load_texture_from_image(thread, &image)
+            .map_err(|e| format!("Couldn't load texture {}: {}", name, e))?;
+        Ok(self.insert(thread, name, texture, TextureFilter::TEXTURE_FILTER_POINT))
+    }
+
+    fn insert(&mut self, thread: &RaylibThread, name: &str, texture: Texture2D, filter: TextureFilter) -> Rc<Texture2D> {
+        texture.set_texture_filter(thread, filter);
+        let texture = Rc::new(texture);
+        self.textures.insert(name.to_string(), Rc::clone(&texture));
+        texture
+    }
+}
+
+// A texture of the given size that was never uploaded, so tests can cut
+// animations from it without a window. Dropping it is safe too, raylib
+// doesn't unload a texture with id 0.
+#[cfg(test)]
+pub fn blank_texture(width: i32, height: i32) -> Texture2D {
+    let raw = raylib::ffi::Texture2D {
+        id: 0,
+        width,
+        height,
+        mipmaps: 1,
+        format: PixelFormat::PIXELFORMAT_UNCOMPRESSED_R8G8B8A8 as i32,
+    };
+    unsafe { Texture2D::from_raw(raw) }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use raylib::prelude::*;
+
+use crate::json::{self, Value};
+use crate::textures::TextureManager;
+use crate::tilemap::{MapObject, Tile, TileAnimation, TileMap, Tileset};
+
+// The top bits of a gid say how the tile is flipped, which isn't supported
+const GID_MASK: u32 = 0x1FFFFFFF;
+
+fn number(value: &Value, name: &str, what: &str) -> Result<f64, String> {
+    value.get(name).and_then(Value::as_f64)
+        .ok_or(format!("{} is missing \"{}\"", what, name))
+}
+
+// Relative paths in a Tiled file are relative to that file
+fn resolve(relative_to: &str, path: &str) -> String {
+    match Path::new(relative_to).parent() {
+        Some(dir) => dir.join(path).to_string_lossy().into_owned(),
+        None => path.to_string(),
+    }
+}
+
+fn read_json(path: &str, what: &str) -> Result<Value, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Couldn't read {} {}: {}", what, path, e))?;
+    json::parse(&text).map_err(|e| format!("Couldn't parse {} {}: {}", what, path, e))
+}
+
+// A tileset embedded in the map, or kept in its own file (exported as JSON)
+// and referenced by "source"
+fn load_tileset(
+    rl: &mut RaylibHandle, thread: &RaylibThread,
+    textures: &mut TextureManager,
+    entry: &Value, map_path: &str,
+) -> Result<Tileset, String> {
+    let first_gid = number(entry, "firstgid", "Tileset")? as u32;
+    let (tileset, tileset_path) = match entry.get("source").and_then(Value::as_str) {
+        Some(source) if source.ends_with(".tsx") => {
+            return Err(format!("Tileset {} is XML, export it from Tiled as JSON", source));
+        }
+        Some(source) => {
+            let path = resolve(map_path, source);
+            (read_json(&path, "tileset")?, path)
+        }
+        None => (entry.clone(), map_path.to_string()),
+    };
+
+    let image = tileset.get("image").and_then(Value::as_str)
+        .ok_or(format!("Tileset in {} has no image, image collections aren't supported", tileset_path))?;
+    let image = resolve(&tileset_path, image);
+    if !Path::new(&image).exists() {
+        return Err(format!("Tileset image {} used by {} doesn't exist", image, tileset_path));
+    }
+
+    // Tiles with an "animation", a list of { tileid, duration } in milliseconds
+    let mut animations = HashMap::new();
+    for tile in tileset.get("tiles").and_then(Value::as_array).unwrap_or_default() {
+        let Some(frames) = tile.get("animation").and_then(Value::as_array) else {
+            continue;
+        };
+        let frames = frames.iter()
+            .map(|frame| Ok((
+                number(frame, "tileid", "Animation frame")? as u32,
+                number(frame, "duration", "Animation frame")? as f32 / 1000.0,
+            )))
+            .collect::<Result<Vec<(u32, f32)>, String>>()?;
+        if !frames.is_empty() {
+            animations.insert(number(tile, "id", "Tile")? as u32, TileAnimation::new(frames));
+        }
+    }
+
+    Ok(Tileset {
+        texture: textures.load(rl, thread, &image)?,
+        first_gid,
+        columns: (number(&tileset, "columns", "Tileset")? as u32).max(1),
+        tile_width: number(&tileset, "tilewidth", "Tileset")? as f32,
+        tile_height: number(&tileset, "tileheight", "Tileset")? as f32,
+        animations,
+    })
+}
+
+// Gids of animated tiles become Tile::Animated up front, so static ones never
+// look for an animation while drawing
+fn tile_layer(layer: &Value, tilesets: &[Tileset]) -> Result<Vec<Vec<Tile>>, String> {
+    let animated = |gid: u32| tilesets.iter().rev()
+        .find(|tileset| tileset.first_gid <= gid)
+        .is_some_and(|tileset| tileset.animations.contains_key(&(gid - tileset.first_gid)));
+    let width = (number(layer, "width", "Tile layer")? as usize).max(1);
+    let data = layer.get("data").and_then(Value::as_array)
+        .ok_or("Tile layer data isn't a list, set the layer format to CSV in Tiled")?;
+    let tiles = data.iter()
+        .map(|gid| match gid.as_f64().map(|gid| gid as u32 & GID_MASK) {
+            Some(0) => Ok(Tile::Empty),
+            Some(gid) if animated(gid) => Ok(Tile::Animated(gid)),
+            Some(gid) => Ok(Tile::Image(gid)),
+            None => Err("Tile layer data has something other than a tile id".to_string()),
+        })
+        .collect::<Result<Vec<Tile>, String>>()?;
+    Ok(tiles.chunks(width).map(<[Tile]>::to_vec).collect())
+}
+
+// An object's kind is its class (type before Tiled 1.9), or failing that the
+// name of its layer, so a layer called "collision" needs nothing set on each
+// rectangle
+fn object_layer(layer: &Value, objects: &mut Vec<MapObject>) -> Result<(), String> {
+    let layer_name = layer.get("name").and_then(Value::as_str).unwrap_or_default();
+    let entries = layer.get("objects").and_then(Value::as_array).unwrap_or_default();
+    for entry in entries {
+        let text = |name: &str| entry.get(name).and_then(Value::as_str).unwrap_or_default();
+        let kind = [text("class"), text("type"), layer_name].into_iter()
+            .find(|kind| !kind.is_empty())
+            .unwrap_or_default();
+        let size = |name: &str| entry.get(name).and_then(Value::as_f64).unwrap_or(0.0) as f32;
+        let properties = entry.get("properties").and_then(Value::as_array).unwrap_or_default().iter()
+            .filter_map(|property| Some((
+                property.get("name").and_then(Value::as_str)?.to_string(),
+                property.get("value")?.clone(),
+            )))
+            .collect();
+        objects.push(MapObject {
+            name: text("name").to_string(),
+            kind: kind.to_string(),
+            rect: Rectangle::new(
+                number(entry, "x", "Object")? as f32,
+                number(entry, "y", "Object")? as f32,
+                size("width"),
+                size("height"),
+            ),
+            properties,
+        });
+    }
+    Ok(())
+}
+
+// Reads a map saved from Tiled as JSON (.tmj): its orthogonal, finite tile
+// layers in CSV format and its object layers. Tiles are square, as big as the
+// map's tile size.
+pub fn load_tiled(
+    rl: &mut RaylibHandle, thread: &RaylibThread,
+    textures: &mut TextureManager,
+    path: &str,
+) -> Result<TileMap, String> {
+    let map = read_json(path, "Tiled map")?;
+    let context = |e: String| format!("Couldn't load Tiled map {}: {}", path, e);
+
+    if map.get("infinite").and_then(Value::as_bool).unwrap_or(false) {
+        return Err(context("infinite maps aren't supported".to_string()));
+    }
+    if let Some(orientation) = map.get("orientation").and_then(Value::as_str)
+        && orientation != "orthogonal"
+    {
+        return Err(context(format!("{} maps aren't supported", orientation)));
+    }
+    let tile_size = number(&map, "tilewidth", "Map").map_err(context)? as f32;
+    if number(&map, "tileheight", "Map").map_err(context)? as f32 != tile_size {
+        return Err(context("tiles have to be square".to_string()));
+    }
+
+    let mut tilesets = Vec::new();
+    for entry in map.get("tilesets").and_then(Value::as_array).unwrap_or_default() {
+        tilesets.push(load_tileset(rl, thread, textures, entry, path).map_err(context)?);
+    }
+    tilesets.sort_by_key(|tileset: &Tileset| tileset.first_gid);
+
+    let mut layers = Vec::new();
+    let mut objects = Vec::new();
+    for layer in map.get("layers").and_then(Value::as_array).unwrap_or_default() {
+        if !layer.get("visible").and_then(Value::as_bool).unwrap_or(true) {
+            continue;
+        }
+        match layer.get("type").and_then(Value::as_str) {
+            Some("tilelayer") => layers.push(tile_layer(layer, &tilesets).map_err(context)?),
+            Some("objectgroup") => object_layer(layer, &mut objects).map_err(context)?,
+            other => eprintln!("WARNING: skipping {} layer in {}", other.unwrap_or("untyped"), path),
+        }
+    }
+
+    Ok(TileMap { tile_size, layers, tilesets, objects })
+}
@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use raylib::prelude::*;
+
+use crate::json::Value;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Tile {
+    Floor,
+    Wall,
+    Empty, // nothing drawn, lets lower layers show through
+    Image(u32), // a tileset tile by Tiled global id
+    Animated(u32), // the same, for a tile that cycles through others
+}
+
+impl Tile {
+    fn from_char(c: char) -> Option<Tile> {
+        match c {
+            '.' => Some(Self::Floor),
+            '#' => Some(Self::Wall),
+            _ => None,
+        }
+    }
+
+    // Image tiles never are, Tiled maps mark what's solid with collision objects
+    fn is_solid(&self) -> bool {
+        matches!(self, Self::Wall)
+    }
+
+    fn color(&self) -> Option<Color> {
+        match self {
+            Self::Floor => Some(Color::get_color(0x242424FF)),
+            Self::Wall => Some(Color::get_color(0x3A3A3AFF)),
+            Self::Empty | Self::Image(_) | Self::Animated(_) => None,
+        }
+    }
+}
+
+// Which tile of the tileset an animated tile shows, stepping on like
+// SpriteAnimation::animate does but with how long each frame holds
+pub struct TileAnimation {
+    frames: Vec<(u32, f32)>, // tile index in the tileset, seconds shown
+    current: usize,
+    timer: f32,
+}
+
+impl TileAnimation {
+    // Frames of no length would never let the loop in update finish
+    pub fn new(frames: Vec<(u32, f32)>) -> TileAnimation {
+        let frames = frames.into_iter().map(|(tile, duration)| (tile, duration.max(0.001))).collect();
+        TileAnimation { frames, current: 0, timer: 0.0 }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        if self.frames.len() < 2 {
+            return;
+        }
+        self.timer += dt;
+        while self.timer >= self.frames[self.current].1 {
+            self.timer -= self.frames[self.current].1;
+            self.current = (self.current + 1) % self.frames.len();
+        }
+    }
+
+    pub fn tile(&self) -> u32 {
+        self.frames[self.current].0
+    }
+}
+
+// A tileset image cut into a grid, owning the global ids from first_gid on
+pub struct Tileset {
+    pub texture: Rc<Texture2D>,
+    pub first_gid: u32,
+    pub columns: u32,
+    pub tile_width: f32,
+    pub tile_height: f32,
+    pub animations: HashMap<u32, TileAnimation>, // by tile index, only the animated ones
+}
+
+// Something placed in the map by hand rather than painted as a tile: a
+// collision rectangle or a named spawn point. Points have no size.
+pub struct MapObject {
+    pub name: String,
+    pub kind: String,
+    pub rect: Rectangle,
+    pub properties: Vec<(String, Value)>, // Tiled's custom properties, by name
+}
+
+impl MapObject {
+    pub fn property(&self, name: &str) -> Option<&Value> {
+        self.properties.iter().find(|(key, _)| key == name).map(|(_, value)| value)
+    }
+}
+
+// Layers of tiles drawn bottom to top, either read from a text file (one layer,
+// one row per line with '#' for walls and '.' for floor) or imported from a
+// Tiled map (see tiled.rs)
+pub struct TileMap {
+    pub tile_size: f32,
+    pub layers: Vec<Vec<Vec<Tile>>>,
+    pub tilesets: Vec<Tileset>, // sorted by first_gid
+    pub objects: Vec<MapObject>,
+}
+
+impl TileMap {
+    pub fn load(path: &str, tile_size: f32) -> Result<TileMap, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read tilemap {}: {}", path, e))?;
+        TileMap::parse(&text, tile_size)
+            .map_err(|e| format!("Couldn't parse tilemap {}: {}", path, e))
+    }
+
+    pub fn parse(text: &str, tile_size: f32) -> Result<TileMap, String> {
+        let mut tiles = Vec::new();
+        for (y, line) in text.lines().enumerate() {
+            let row = line.chars().enumerate()
+                .map(|(x, c)| Tile::from_char(c)
+                    .ok_or(format!("unknown tile '{}' at line {}, column {}", c, y + 1, x + 1)))
+                .collect::<Result<Vec<Tile>, String>>()?;
+            tiles.push(row);
+        }
+        Ok(TileMap { tile_size, layers: vec![tiles], tilesets: Vec::new(), objects: Vec::new() })
+    }
+
+    fn tile_rect(&self, x: usize, y: usize) -> Rectangle {
+        Rectangle::new(x as f32 * self.tile_size, y as f32 * self.tile_size, self.tile_size, self.tile_size)
+    }
+
+    // The area the map covers, as wide as its longest row in any layer
+    pub fn bounds(&self) -> Rectangle {
+        let columns = self.layers.iter().flatten().map(Vec::len).max().unwrap_or(0);
+        let rows = self.layers.iter().map(Vec::len).max().unwrap_or(0);
+        Rectangle::new(0.0, 0.0, columns as f32 * self.tile_size, rows as f32 * self.tile_size)
+    }
+
+    pub fn solid_rects(&self) -> Vec<Rectangle> {
+        let mut rects = Vec::new();
+        for layer in &self.layers {
+            for (y, row) in layer.iter().enumerate() {
+                for (x, tile) in row.iter().enumerate() {
+                    if tile.is_solid() {
+                        rects.push(self.tile_rect(x, y));
+                    }
+                }
+            }
+        }
+        rects.extend(self.objects_of_kind("collision").map(|object| object.rect));
+        rects
+    }
+
+    // A wall tile in any layer, or a cell a collision object covers. Outside
+    // the map nothing is.
+    pub fn is_solid_cell(&self, x: i64, y: i64) -> bool {
+        if x < 0 || y < 0 {
+            return false;
+        }
+        let (x, y) = (x as usize, y as usize);
+        let wall = self.layers.iter()
+            .any(|layer| layer.get(y).and_then(|row| row.get(x)).is_some_and(Tile::is_solid));
+        // Shrunk a little so an object only touching the cell's edge doesn't count
+        let cell = self.tile_rect(x, y);
+        let inner = Rectangle::new(cell.x + 1.0, cell.y + 1.0, cell.width - 2.0, cell.height - 2.0);
+        wall || self.objects_of_kind("collision").any(|object| object.rect.check_collision_recs(&inner))
+    }
+
+    // pos itself when it's over an open cell of the map, otherwise the middle
+    // of the closest open one, None if there are none
+    pub fn nearest_open(&self, pos: Vector2) -> Option<Vector2> {
+        let bounds = self.bounds();
+        let columns = (bounds.width / self.tile_size) as i64;
+        let rows = (bounds.height / self.tile_size) as i64;
+        if columns == 0 || rows == 0 {
+            return None;
+        }
+        let open = |x: i64, y: i64| (0..columns).contains(&x) && (0..rows).contains(&y) && !self.is_solid_cell(x, y);
+        let x = ((pos.x / self.tile_size).floor() as i64).clamp(0, columns - 1);
+        let y = ((pos.y / self.tile_size).floor() as i64).clamp(0, rows - 1);
+        if bounds.check_collision_point_rec(pos) && open(x, y) {
+            return Some(pos);
+        }
+
+        // Rings of cells further and further out, the closest open one in
+        // the first ring that has any
+        for radius in 0..columns.max(rows) {
+            let closest = (-radius..=radius)
+                .flat_map(|dy| (-radius..=radius).map(move |dx| (x + dx, y + dy)))
+                .filter(|&(cx, cy)| (cx - x).abs() == radius || (cy - y).abs() == radius)
+                .filter(|&(cx, cy)| open(cx, cy))
+                .map(|(cx, cy)| {
+                    let cell = self.tile_rect(cx as usize, cy as usize);
+                    Vector2::new(cell.x + cell.width / 2.0, cell.y + cell.height / 2.0)
+                })
+                .min_by(|a, b| a.distance_to(pos).total_cmp(&b.distance_to(pos)));
+            if closest.is_some() {
+                return closest;
+            }
+        }
+        None
+    }
+
+    // Steps through every cell the segment from a to b crosses (a DDA grid
+    // walk), false as soon as one of them is solid
+    pub fn has_line_of_sight(&self, a: Vector2, b: Vector2) -> bool {
+        let start = a / self.tile_size;
+        let end = b / self.tile_size;
+        let (mut x, mut y) = (start.x.floor() as i64, start.y.floor() as i64);
+        let (end_x, end_y) = (end.x.floor() as i64, end.y.floor() as i64);
+        let dir = end - start;
+        let step_x = if dir.x > 0.0 { 1 } else { -1 };
+        let step_y = if dir.y > 0.0 { 1 } else { -1 };
+
+        // How far along the segment, from 0 to 1, each axis next crosses a
+        // cell edge, and how far apart those crossings are
+        let first_crossing = |pos: f32, cell: i64, dir: f32| {
+            if dir > 0.0 {
+                ((cell + 1) as f32 - pos) / dir
+            } else if dir < 0.0 {
+                (cell as f32 - pos) / dir
+            } else {
+                f32::INFINITY
+            }
+        };
+        let mut next_x = first_crossing(start.x, x, dir.x);
+        let mut next_y = first_crossing(start.y, y, dir.y);
+        let delta_x = if dir.x != 0.0 { 1.0 / dir.x.abs() } else { f32::INFINITY };
+        let delta_y = if dir.y != 0.0 { 1.0 / dir.y.abs() } else { f32::INFINITY };
+
+        loop {
+            if self.is_solid_cell(x, y) {
+                return false;
+            }
+            if (x, y) == (end_x, end_y) || (next_x > 1.0 && next_y > 1.0) {
+                return true;
+            }
+            if next_x < next_y {
+                x += step_x;
+                next_x += delta_x;
+            } else {
+                y += step_y;
+                next_y += delta_y;
+            }
+        }
+    }
+
+    pub fn objects_of_kind<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = &'a MapObject> {
+        self.objects.iter().filter(move |object| object.kind == kind)
+    }
+
+    // Tiles of one layer overlapping view, without looking at the ones outside it
+    pub fn visible_tiles(&self, layer: usize, view: Rectangle) -> impl Iterator<Item = (usize, usize, Tile)> + '_ {
+        let first_x = (view.x / self.tile_size).floor().max(0.0) as usize;
+        let first_y = (view.y / self.tile_size).floor().max(0.0) as usize;
+        let last_x = ((view.x + view.width) / self.tile_size).ceil().max(0.0) as usize;
+        let last_y = ((view.y + view.height) / self.tile_size).ceil().max(0.0) as usize;
+
+        self.layers[layer].iter().enumerate().take(last_y).skip(first_y).flat_map(move |(y, row)| {
+            row.iter().enumerate().take(last_x).skip(first_x).map(move |(x, tile)| (x, y, *tile))
+        })
+    }
+
+    fn tileset_for(&self, gid: u32) -> Option<&Tileset> {
+        self.tilesets.iter().rev().find(|tileset| tileset.first_gid <= gid)
+    }
+
+    // Animations all run on one clock, so every tile of a kind moves in step
+    pub fn update(&mut self, dt: f32) {
+        for tileset in &mut self.tilesets {
+            for animation in tileset.animations.values_mut() {
+                animation.update(dt);
+            }
+        }
+    }
+
+    fn draw_image_tile(&self, d: &mut RaylibDrawHandle, gid: u32, animated: bool, dest: Rectangle) {
+        let Some(tileset) = self.tileset_for(gid) else {
+            return;
+        };
+        let mut index = gid - tileset.first_gid;
+        if animated && let Some(animation) = tileset.animations.get(&index) {
+            index = animation.tile();
+        }
+        let source = Rectangle::new(
+            (index % tileset.columns) as f32 * tileset.tile_width,
+            (index / tileset.columns) as f32 * tileset.tile_height,
+            tileset.tile_width,
+            tileset.tile_height,
+        );
+        d.draw_texture_pro(&*tileset.texture, source, dest, Vector2::zero(), 0.0, Color::WHITE);
+    }
+
+    pub fn draw(&self, d: &mut RaylibDrawHandle, view: Rectangle) {
+        for layer in 0..self.layers.len() {
+            for (x, y, tile) in self.visible_tiles(layer, view) {
+                let rect = self.tile_rect(x, y);
+                match tile {
+                    Tile::Image(gid) => self.draw_image_tile(d, gid, false, rect),
+                    Tile::Animated(gid) => self.draw_image_tile(d, gid, true, rect),
+                    _ => if let Some(color) = tile.color() {
+                        d.draw_rectangle_rec(rect, color);
+                    },
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_visible_tiles_are_iterated() {
+        let row = ".".repeat(100);
+        let text = vec![row; 100].join("\n");
+        let map = TileMap::parse(&text, 32.0).unwrap();
+        assert_eq!(map.layers[0].iter().map(Vec::len).sum::<usize>(), 10_000);
+
+        // Four and a half tiles each way from a tile corner, so five rows
+        // and columns are touched
+        let view = Rectangle::new(320.0, 640.0, 144.0, 144.0);
+        let visible: Vec<(usize, usize, Tile)> = map.visible_tiles(0, view).collect();
+        assert_eq!(visible.len(), 25);
+        assert!(visible.iter().all(|&(x, y, _)| (10..15).contains(&x) && (20..25).contains(&y)));
+    }
+
+    const WALLED: &str = "\
+.....
+..#..
+..#..
+.....";
+
+    #[test]
+    fn a_wall_blocks_line_of_sight() {
+        let map = TileMap::parse(WALLED, 32.0).unwrap();
+        assert!(!map.has_line_of_sight(Vector2::new(16.0, 48.0), Vector2::new(144.0, 80.0)));
+        assert!(!map.has_line_of_sight(Vector2::new(144.0, 80.0), Vector2::new(16.0, 48.0)));
+    }
+
+    #[test]
+    fn a_clear_line_has_line_of_sight() {
+        let map = TileMap::parse(WALLED, 32.0).unwrap();
+        // Along the open top row, and down the open first column
+        assert!(map.has_line_of_sight(Vector2::new(16.0, 16.0), Vector2::new(144.0, 16.0)));
+        assert!(map.has_line_of_sight(Vector2::new(16.0, 16.0), Vector2::new(16.0, 112.0)));
+        assert!(map.has_line_of_sight(Vector2::new(40.0, 40.0), Vector2::new(40.0, 40.0)));
+    }
+}
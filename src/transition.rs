@@ -0,0 +1,53 @@
+use raylib::prelude::*;
+
+use crate::screen::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
+// A fade to black and back over duration seconds. The screen is fully black
+// at the midpoint, which is when whatever it covers should be swapped out.
+pub struct Transition {
+    pub duration: f32,
+    elapsed: f32,
+    passed_midpoint: bool,
+}
+
+impl Transition {
+    // Starts out finished, so nothing is drawn until start
+    pub fn new(duration: f32) -> Transition {
+        Transition { duration, elapsed: duration, passed_midpoint: true }
+    }
+
+    pub fn start(&mut self) {
+        self.elapsed = 0.0;
+        self.passed_midpoint = false;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    // True on the one update that passes the midpoint
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+        if self.passed_midpoint || self.elapsed < self.duration / 2.0 {
+            return false;
+        }
+        self.passed_midpoint = true;
+        true
+    }
+
+    // Ramps from 0 up to 1 at the midpoint and back down
+    pub fn alpha(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return 0.0;
+        }
+        let progress = self.elapsed / self.duration;
+        1.0 - (progress * 2.0 - 1.0).abs()
+    }
+
+    // Screen space, on top of everything
+    pub fn draw(&self, d: &mut RaylibDrawHandle) {
+        if !self.is_finished() {
+            d.draw_rectangle(0, 0, VIRTUAL_WIDTH, VIRTUAL_HEIGHT, Color::BLACK.alpha(self.alpha()));
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use raylib::prelude::*;
+
+use crate::json::Value;
+use crate::tilemap::MapObject;
+
+// What a trigger does, picked by its object's name in the map
+#[derive(PartialEq, Debug, Clone)]
+pub enum TriggerAction {
+    Dialogue(String), // "dialogue" with a "text" property
+    SpawnEnemies(usize), // "spawn_enemies" with an optional "count", 1 by default
+    ChangeMap { map: String, spawn: String }, // "door" with a "map" and an optional "spawn", "player" by default
+    Checkpoint, // "checkpoint", where the players come back after dying
+}
+
+// An invisible area that fires its action when a player walks into it. It
+// fires again each time someone enters after everyone has left, unless it's
+// a one-shot.
+pub struct Trigger {
+    pub rect: Rectangle,
+    pub once: bool,
+    pub action: TriggerAction,
+    pub fired: bool, // gone off at least once
+    occupied: bool, // someone was inside on the last update
+}
+
+impl Trigger {
+    pub fn new(rect: Rectangle, once: bool, action: TriggerAction) -> Trigger {
+        Trigger { rect, once, action, fired: false, occupied: false }
+    }
+
+    // A "trigger" object, with a bool "once" property to make it a one-shot
+    pub fn from_object(object: &MapObject) -> Result<Trigger, String> {
+        let action = match object.name.as_str() {
+            "dialogue" => {
+                let text = object.property("text").and_then(Value::as_str)
+                    .ok_or("Dialogue trigger is missing \"text\"")?;
+                TriggerAction::Dialogue(text.to_string())
+            }
+            "spawn_enemies" => {
+                let count = object.property("count").map(|count| count.as_f64()
+                    .filter(|&count| count >= 1.0)
+                    .ok_or("Enemy trigger has an invalid \"count\""))
+                    .transpose()?
+                    .unwrap_or(1.0);
+                TriggerAction::SpawnEnemies(count as usize)
+            }
+            "door" => {
+                let text = |name: &str| object.property(name).and_then(Value::as_str);
+                let map = text("map").ok_or("Door trigger is missing \"map\"")?;
+                TriggerAction::ChangeMap { map: map.to_string(), spawn: text("spawn").unwrap_or("player").to_string() }
+            }
+            "checkpoint" => TriggerAction::Checkpoint,
+            other => return Err(format!("Unknown trigger action \"{}\"", other)),
+        };
+        let once = object.property("once").and_then(Value::as_bool).unwrap_or(false);
+        Ok(Trigger::new(object.rect, once, action))
+    }
+
+    // True on the update something first steps inside
+    pub fn update(&mut self, bodies: &[Rectangle]) -> bool {
+        let occupied = bodies.iter().any(|body| body.check_collision_recs(&self.rect));
+        let entered = occupied && !self.occupied;
+        self.occupied = occupied;
+        if !entered || (self.once && self.fired) {
+            return false;
+        }
+        self.fired = true;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INSIDE: Rectangle = Rectangle { x: 10.0, y: 10.0, width: 8.0, height: 8.0 };
+    const OUTSIDE: Rectangle = Rectangle { x: 100.0, y: 100.0, width: 8.0, height: 8.0 };
+
+    fn trigger(once: bool) -> Trigger {
+        Trigger::new(Rectangle::new(0.0, 0.0, 32.0, 32.0), once, TriggerAction::Checkpoint)
+    }
+
+    #[test]
+    fn one_shot_fires_only_once() {
+        let mut trigger = trigger(true);
+        assert!(trigger.update(&[INSIDE]));
+        assert!(!trigger.update(&[INSIDE]));
+        assert!(!trigger.update(&[OUTSIDE]));
+        assert!(!trigger.update(&[INSIDE]));
+    }
+
+    #[test]
+    fn others_fire_on_each_entry() {
+        let mut trigger = trigger(false);
+        assert!(trigger.update(&[INSIDE]));
+        assert!(!trigger.update(&[INSIDE]));
+        assert!(!trigger.update(&[OUTSIDE]));
+        assert!(trigger.update(&[INSIDE]));
+        // Someone joining whoever's already inside isn't a new entry
+        assert!(!trigger.update(&[INSIDE, INSIDE]));
+    }
+}
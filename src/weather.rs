@@ -0,0 +1,97 @@
+use std::f32::consts::TAU;
+
+use raylib::prelude::*;
+
+use crate::particles::Particle;
+use crate::rng::Rng;
+use crate::screen::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
+const MARGIN: f32 = 16.0; // pixels past the screen edges things wrap around at
+const RAIN_COLOR: Color = Color { r: 170, g: 190, b: 230, a: 150 };
+const SNOW_COLOR: Color = Color { r: 240, g: 240, b: 255, a: 200 };
+const RAIN_STREAK: f32 = 0.025; // seconds of fall a raindrop is drawn stretched over
+const SNOW_SWAY: f32 = 20.0; // pixels per second either way a flake drifts
+const SNOW_SWAY_RATE: f32 = 1.5; // sways a second, roughly
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum WeatherKind {
+    Rain,
+    Snow,
+}
+
+impl WeatherKind {
+    pub fn from_name(name: &str) -> Option<WeatherKind> {
+        match name {
+            "rain" => Some(Self::Rain),
+            "snow" => Some(Self::Snow),
+            _ => None,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct WeatherSettings {
+    pub kind: WeatherKind,
+    pub density: usize, // how many are on screen at once
+    pub wind: f32, // pixels per second sideways, positive blows right
+    pub sound: Option<&'static str>, // looped for as long as it lasts
+}
+
+// Rain or snow falling across the whole screen, drawn over the world in
+// screen space. It's only for looks, so it has its own random numbers and
+// never touches the game's. The same particles wrap around the edges
+// forever rather than new ones being spawned.
+pub struct Weather {
+    pub kind: WeatherKind,
+    wind: f32,
+    particles: Vec<Particle>, // age only drives a snowflake's sway
+}
+
+impl Weather {
+    pub fn new(settings: &WeatherSettings) -> Weather {
+        let mut rng = Rng::new(0xC2B2AE35);
+        let (w, h) = (VIRTUAL_WIDTH as f32, VIRTUAL_HEIGHT as f32);
+        let particles = (0..settings.density).map(|_| {
+            let (fall, size, color) = match settings.kind {
+                WeatherKind::Rain => (rng.range_f32(380.0, 460.0), 1.0, RAIN_COLOR),
+                WeatherKind::Snow => (rng.range_f32(30.0, 60.0), rng.range_f32(1.5, 3.0), SNOW_COLOR),
+            };
+            Particle {
+                pos: Vector2::new(rng.range_f32(-MARGIN, w + MARGIN), rng.range_f32(-MARGIN, h + MARGIN)),
+                velocity: Vector2::new(settings.wind, fall),
+                lifetime: f32::INFINITY,
+                age: rng.range_f32(0.0, TAU), // so the flakes don't all sway together
+                color,
+                size,
+            }
+        }).collect();
+        Weather { kind: settings.kind, wind: settings.wind, particles }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let (w, h) = (VIRTUAL_WIDTH as f32 + MARGIN * 2.0, VIRTUAL_HEIGHT as f32 + MARGIN * 2.0);
+        for particle in &mut self.particles {
+            particle.age += dt;
+            particle.velocity.x = match self.kind {
+                WeatherKind::Rain => self.wind,
+                WeatherKind::Snow => self.wind + (particle.age * SNOW_SWAY_RATE).sin() * SNOW_SWAY,
+            };
+            particle.pos += particle.velocity * dt;
+            particle.pos.x = (particle.pos.x + MARGIN).rem_euclid(w) - MARGIN;
+            particle.pos.y = (particle.pos.y + MARGIN).rem_euclid(h) - MARGIN;
+        }
+    }
+
+    // Screen space, over the world and under the HUD
+    pub fn draw(&self, d: &mut RaylibDrawHandle) {
+        for particle in &self.particles {
+            match self.kind {
+                WeatherKind::Rain => {
+                    let tail = particle.pos - particle.velocity * RAIN_STREAK;
+                    d.draw_line_ex(tail, particle.pos, particle.size, particle.color);
+                }
+                WeatherKind::Snow => d.draw_circle_v(particle.pos, particle.size, particle.color),
+            }
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use raylib::prelude::*;
+
+use crate::pathfinding::NavGrid;
+use crate::physics::{CollisionFilter, WALLS, can_collide};
+
+// Positions are plain f32 pixels, which get coarser the further they are
+// from the origin: f32 steps 2^-23 of the value's power of two, so within
+// 65536 of it they're still 1/128 px apart, far below the few pixels anything
+// moves per step. Keeping everything inside this is much simpler than chunked
+// or f64 positions and leaves room for levels hundreds of screens across; a
+// world that needs more should move to chunk-relative positions instead.
+pub const WORLD_BOUNDS: Rectangle = Rectangle { x: -65536.0, y: -65536.0, width: 131072.0, height: 131072.0 };
+
+// Time in play, shared by everything that needs to know how long it's been
+// rather than each keeping its own count. It only runs while playing: it
+// stands still while paused, in a menu or fading between maps.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct GameClock {
+    pub tick: u64, // updates so far
+    pub elapsed: f32, // seconds, the sum of those updates' dt
+}
+
+impl GameClock {
+    pub fn advance(&mut self, dt: f32) {
+        self.tick += 1;
+        self.elapsed += dt;
+    }
+}
+
+pub struct World {
+    pub obstacles: Vec<Rectangle>,
+    pub bounds: Rectangle, // playable area, nothing should leave it
+    pub nav: Option<NavGrid>, // for pathfinding, see build_nav_grid
+    pub clock: GameClock, // carried over whenever the map changes
+}
+
+impl World {
+    // Bounds reaching past WORLD_BOUNDS are cut back to it, so clamping to
+    // them always keeps things where positions are precise
+    pub fn new(bounds: Rectangle) -> World {
+        let x = bounds.x.max(WORLD_BOUNDS.x);
+        let y = bounds.y.max(WORLD_BOUNDS.y);
+        let right = (bounds.x + bounds.width).min(WORLD_BOUNDS.x + WORLD_BOUNDS.width);
+        let bottom = (bounds.y + bounds.height).min(WORLD_BOUNDS.y + WORLD_BOUNDS.height);
+        World {
+            obstacles: Vec::new(),
+            bounds: Rectangle::new(x, y, (right - x).max(0.0), (bottom - y).max(0.0)),
+            nav: None,
+            clock: GameClock::default(),
+        }
+    }
+
+    pub fn add_obstacle(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        self.obstacles.push(Rectangle::new(x, y, width, height));
+    }
+
+    // The obstacles, or none for something that passes through walls
+    pub fn obstacles_for(&self, filter: CollisionFilter) -> &[Rectangle] {
+        if can_collide(filter, WALLS) { &self.obstacles } else { &[] }
+    }
+
+    // Call once every obstacle is in, the grid doesn't follow later changes
+    pub fn build_nav_grid(&mut self, tile_size: f32, diagonal: bool) {
+        self.nav = Some(NavGrid::new(self.bounds, tile_size, &self.obstacles, diagonal));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_steps_stay_precise_at_the_edges() {
+        let right = WORLD_BOUNDS.x + WORLD_BOUNDS.width;
+        let bottom = WORLD_BOUNDS.y + WORLD_BOUNDS.height;
+        // An awkward fraction of a pixel, less than anything walks in an update
+        let step: f32 = 1.0 / 7.0;
+        for edge in [WORLD_BOUNDS.x, WORLD_BOUNDS.y, right, bottom] {
+            let start = edge - step.copysign(edge); // just inside
+            let moved = (start + step) - start;
+            assert!((moved - step).abs() <= 1.0 / 128.0);
+        }
+    }
+
+    #[test]
+    fn bounds_are_cut_back_to_the_world_bounds() {
+        let world = World::new(Rectangle::new(-100_000.0, 0.0, 300_000.0, 100.0));
+        assert_eq!(world.bounds.x, WORLD_BOUNDS.x);
+        assert_eq!(world.bounds.width, WORLD_BOUNDS.width);
+        assert_eq!(world.bounds.height, 100.0);
+    }
+}